@@ -0,0 +1,221 @@
+//! The cache of commit authors and pull requests to forge logins.
+
+use crate::error::ResultExt;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A login resolved from the forge API.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedLogin {
+    /// `None` when the forge has no account for the email or no such pull request.
+    pub login: Option<String>,
+    /// Unix timestamp of the lookup.
+    pub updated_at: i64,
+}
+
+impl CachedLogin {
+    fn new(login: Option<&str>) -> CachedLogin {
+        CachedLogin {
+            login: login.map(str::to_owned),
+            updated_at: Utc::now().timestamp(),
+        }
+    }
+}
+
+/// The on-disk cache of commit author to forge login mappings,
+/// usually `.changelog/.authors.json`.
+///
+/// ```json
+/// {
+///   "overrides": { "Alice": "alice", "bob@example.com": "bob" },
+///   "entries": { "alice@example.com": { "login": "alice", "updated_at": 1634567890 } },
+///   "pulls": { "1234": { "login": "alice", "updated_at": 1634567890 } }
+/// }
+/// ```
+///
+/// Override keys are author emails or names, emails are looked up first.
+/// `entries` are keyed by the commit email and `pulls` by the pull request
+/// number, a `null` login marks an email without an account or a number the
+/// forge does not know as a pull request. Both expire after `ttl`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AuthorCache {
+    /// Mappings written by hand, they never expire and win over `entries`.
+    #[serde(default)]
+    pub overrides: BTreeMap<String, String>,
+    /// The account of each commit email looked up.
+    #[serde(default)]
+    pub entries: BTreeMap<String, CachedLogin>,
+    /// The author of each pull request looked up.
+    #[serde(default)]
+    pub pulls: BTreeMap<u64, CachedLogin>,
+    /// Mappings from the `authors_file`, they win over `overrides` and are never saved.
+    #[serde(skip)]
+    file_overrides: BTreeMap<String, String>,
+    #[serde(skip)]
+    path: PathBuf,
+    /// Entries older than this many seconds are ignored, 0 keeps them forever.
+    #[serde(skip)]
+    ttl: i64,
+}
+
+impl AuthorCache {
+    /**
+     * 读取缓存文件，文件不存在时返回空的缓存
+     */
+    pub fn load(path: &Path, ttl_days: u64) -> crate::Result<AuthorCache> {
+        let mut cache = if path.is_file() {
+            let data = fs::read_to_string(path)?;
+            serde_json::from_str(&data).with_context(|_| crate::ErrorKind::Config {
+                path: path.display().to_string(),
+            })?
+        } else {
+            AuthorCache::default()
+        };
+
+        cache.path = path.to_owned();
+        cache.ttl = (ttl_days * 24 * 60 * 60) as i64;
+
+        Ok(cache)
+    }
+
+    /// Add mappings that win over the cached entries, e.g. from the `authors_file`.
+    ///
+    /// They only last for this run, removing a line from the file removes the mapping.
+    pub fn add_overrides(&mut self, overrides: BTreeMap<String, String>) {
+        self.file_overrides.extend(overrides);
+    }
+
+    /// The login of an author, `Some(None)` when the email is known to have
+    /// no account and `None` when it was not looked up.
+    ///
+    /// Expired entries are skipped unless `allow_expired`, overrides never expire.
+    #[must_use]
+    pub fn get(&self, email: &str, name: &str, allow_expired: bool) -> Option<Option<&str>> {
+        for overrides in [&self.file_overrides, &self.overrides] {
+            for key in [email, name] {
+                if let Some(login) = overrides.get(key).filter(|_| !key.is_empty()) {
+                    return Some(Some(login));
+                }
+            }
+        }
+
+        self.entries
+            .get(email)
+            .filter(|entry| allow_expired || !self.is_expired(entry))
+            .map(|entry| entry.login.as_deref())
+    }
+
+    /// Remember the account of a commit email, `None` when it has none.
+    ///
+    /// Only for logins known to belong to the commit author. The author of a
+    /// pull request goes to [`insert_pull`](AuthorCache::insert_pull), it may
+    /// be someone else than the author of the merged commits.
+    pub fn insert(&mut self, email: &str, login: Option<&str>) {
+        if !email.is_empty() {
+            self.entries
+                .insert(email.to_owned(), CachedLogin::new(login));
+        }
+    }
+
+    /// The cached author of a pull request, `Some(None)` when the number is
+    /// known not to be a pull request and `None` when it was not looked up.
+    ///
+    /// Expired entries are skipped unless `allow_expired`.
+    #[must_use]
+    pub fn get_pull(&self, number: u64, allow_expired: bool) -> Option<Option<&str>> {
+        self.pulls
+            .get(&number)
+            .filter(|entry| allow_expired || !self.is_expired(entry))
+            .map(|entry| entry.login.as_deref())
+    }
+
+    /// Remember the author of a pull request, `None` when there is no such pull request.
+    pub fn insert_pull(&mut self, number: u64, login: Option<&str>) {
+        self.pulls.insert(number, CachedLogin::new(login));
+    }
+
+    fn is_expired(&self, entry: &CachedLogin) -> bool {
+        self.ttl != 0 && Utc::now().timestamp() - entry.updated_at >= self.ttl
+    }
+
+    /**
+     * 写回缓存文件，目录不存在时会创建
+     */
+    pub fn save(&self) -> crate::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let data = serde_json::to_string_pretty(self).context(crate::ErrorKind::Other)?;
+        fs::write(&self.path, data + "\n")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_overrides_are_not_saved() {
+        let dir =
+            std::env::temp_dir().join(format!("pro-changelog-authors-{}", std::process::id()));
+        let path = dir.join(".authors.json");
+
+        let mut cache = AuthorCache::load(&path, 30).unwrap();
+        cache
+            .overrides
+            .insert("Alice".to_owned(), "alice-old".to_owned());
+        cache.add_overrides(BTreeMap::from([("Alice".to_owned(), "alice".to_owned())]));
+        cache.insert("bob@example.com", Some("bob"));
+        assert_eq!(cache.get("", "Alice", false), Some(Some("alice")));
+        cache.save().unwrap();
+
+        let cache = AuthorCache::load(&path, 30).unwrap();
+        assert_eq!(cache.get("", "Alice", false), Some(Some("alice-old")));
+        assert_eq!(
+            cache.get("bob@example.com", "Bob", false),
+            Some(Some("bob"))
+        );
+        assert_eq!(cache.get("", "Bob", false), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn expired_entries_are_only_used_offline() {
+        let mut cache = AuthorCache::load(Path::new("missing/.authors.json"), 1).unwrap();
+        cache.insert("alice@example.com", Some("alice"));
+        cache.insert("carol@example.com", None);
+        cache.insert_pull(7, Some("bob"));
+        cache.insert_pull(8, None);
+        assert_eq!(
+            cache.get("alice@example.com", "", false),
+            Some(Some("alice"))
+        );
+        assert_eq!(cache.get("carol@example.com", "", false), Some(None));
+        assert_eq!(cache.get_pull(7, false), Some(Some("bob")));
+        assert_eq!(cache.get_pull(8, false), Some(None));
+
+        let two_days_ago = Utc::now().timestamp() - 2 * 24 * 60 * 60;
+        for entry in cache.entries.values_mut().chain(cache.pulls.values_mut()) {
+            entry.updated_at = two_days_ago;
+        }
+        assert_eq!(cache.get("alice@example.com", "", false), None);
+        assert_eq!(cache.get_pull(7, false), None);
+        assert_eq!(
+            cache.get("alice@example.com", "", true),
+            Some(Some("alice"))
+        );
+        assert_eq!(cache.get_pull(7, true), Some(Some("bob")));
+
+        cache.ttl = 0;
+        assert_eq!(cache.get_pull(8, false), Some(None));
+    }
+}
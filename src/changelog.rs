@@ -1,313 +1,690 @@
-﻿use crate::{Commit, Tag};
+﻿//! Changelogs of the packages, one release per tag, built as a
+//! [`model::Changelog`](crate::model::Changelog) and rendered to markdown, html or the Keep a Changelog format.
+
+use crate::authors::AuthorCache;
+use crate::config::Config;
+use crate::error::ResultExt;
+use crate::forge::{self, Forge, ForgeKind, ForgeRepo};
+use crate::git::get_version;
+use crate::html::{HtmlOptions, HtmlRenderer};
+use crate::http::HttpClient;
+use crate::keep_a_changelog::{self, CompareLinks};
+use crate::model::{Author, Changelog, Entry, PackageChangelog, Release, Section, UNRELEASED};
+use crate::package::{self, Package};
+use crate::remote;
+use crate::template::Templates;
+use crate::{Commit, Tag};
 use git2::Repository;
-use regex::Regex;
-use reqwest::Client;
-use serde::Deserialize;
-use std::{collections::HashMap, env, ops::Index};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+};
 
+/// Generates the changelogs of the packages of a repository.
 pub struct Changelogs {
     repo: Repository,
-    author_github_map: HashMap<String, String>,
-    client: Client,
-    github_html_url: String,
-    repo_name: String,
+    config: Config,
+    package_list: Vec<Package>,
+    templates: Templates,
+    html: HtmlRenderer,
+    forge: Box<dyn Forge>,
+    author_cache: AuthorCache,
 }
 
+/// The generated changelog of a package.
 #[derive(Debug)]
-pub struct MARKDOWN {
+pub struct Markdown {
+    /// The package scope, also the file name, e.g. `layout` for `layout.md`.
     pub package: String,
     pub content: String,
 }
 
-#[derive(Deserialize)]
-struct GithubUser {
-    login: String,
-}
-
-#[derive(Deserialize)]
-struct GithubPull {
-    user: GithubUser,
-}
-
-#[derive(Deserialize)]
-struct GithubRepo {
-    html_url: String,
-}
-
 impl Changelogs {
-    pub fn get_md_message(&mut self, commit: &Commit) -> String {
-        let message = commit.message().split("\n").nth(0).unwrap().trim();
+    /**
+     * 把 commit 转化为 changelog 中的一行，有 pr 的会带上 pr 的链接和作者
+     */
+    pub fn get_entry(&mut self, commit: &Commit) -> crate::Result<Entry> {
+        let message = commit.message().lines().next().unwrap_or_default().trim();
 
-        let author = commit.author().as_ref().unwrap();
         let md_hash = commit.hash().trim();
-        let short_md_hash = &md_hash[0..7];
-
-        let re = Regex::new(r"\(#[0-9]*\)").unwrap();
-
-        if re.is_match(message) {
-            let pr_id = re
-                .captures(message)
-                .unwrap()
-                .index(0)
-                .replace("(", "")
-                .replace(")", "");
-            let github_user_id = self.get_pr_user_name(&pr_id, author);
-            let pr_url = format!(
-                "{github_url}/pull/{pr_id}",
-                github_url = self.github_html_url,
-                pr_id = pr_id
-            );
-
-            let md_message = format!(
-                    "{message}. [{pr_id}]({pr_url}) [@{github_user_id}](https://github.com/{github_user_id})",
-                    pr_id = pr_id,
-                    message = message,
-                    pr_url = pr_url,
-                    github_user_id = github_user_id,
-                );
-
-            return md_message;
+        let short_md_hash = md_hash.get(..7).unwrap_or(md_hash);
+        let parsed = commit.parsed().cloned();
+
+        let commit_url = self.forge.commit_url(short_md_hash);
+
+        let mut entry = Entry {
+            message: message.to_owned(),
+            commit_type: parsed
+                .as_ref()
+                .map(|parsed| parsed.commit_type.clone())
+                .unwrap_or_default(),
+            scopes: parsed
+                .as_ref()
+                .map(|parsed| parsed.scopes.clone())
+                .unwrap_or_default(),
+            subject: parsed
+                .as_ref()
+                .map_or_else(|| message.to_owned(), |parsed| parsed.subject.clone()),
+            breaking: parsed.as_ref().is_some_and(|parsed| parsed.breaking),
+            hash: md_hash.to_owned(),
+            commit_url,
+            pr: None,
+            pr_reference: None,
+            pr_url: None,
+            authors: vec![],
+        };
+
+        let mut author_list: Vec<Author> = vec![];
+
+        // pr 的作者，没有 pr 或者 (#123) 不是 pr 时使用 commit 的作者
+        let pull_request = parsed
+            .as_ref()
+            .and_then(|parsed| self.forge.pull_request_number(parsed));
+        let pr_author = match pull_request {
+            Some(pull_request) => self.get_pr_user_name(pull_request, commit)?,
+            None => None,
+        };
+        match (pull_request, pr_author) {
+            (Some(pull_request), Some(author)) => {
+                author_list.push(author);
+                entry.pr = Some(pull_request);
+                entry.pr_reference = Some(self.forge.pull_request_reference(pull_request));
+                entry.pr_url = Some(self.forge.pull_request_url(pull_request));
+            }
+            _ => author_list.push(self.get_user_name(commit)?),
         }
 
-        let commit_or_pr_url = format!(
-            "{github_url}/commit/{short_md_hash}",
-            github_url = self.github_html_url,
-            short_md_hash = short_md_hash
-        );
+        // Co-authored-by 中的作者也要列出来
+        for (name, email) in parsed.map(|parsed| parsed.co_authors()).unwrap_or_default() {
+            author_list.push(self.resolve_author(&email, &name));
+        }
 
-        let md_message = format!(
-            "{message}. [{short_md_hash}]({commit_or_pr_url})",
-            short_md_hash = short_md_hash,
-            message = message,
-            commit_or_pr_url = commit_or_pr_url,
-        );
+        for author in author_list {
+            if entry
+                .authors
+                .iter()
+                .any(|added| added.login == author.login)
+            {
+                continue;
+            }
+            entry.authors.push(author);
+        }
 
-        md_message
+        Ok(entry)
     }
+
+    /**
+     * 把 commit 按照 config.sections 分组，只保留 scope 是当前包的，空的分组会被去掉
+     */
     pub fn gen_change_log_by_commit_list(
         &mut self,
         commit_list: Vec<Commit>,
         package: &str,
-    ) -> crate::Result<Vec<String>> {
-        let mut changelog_list: Vec<String> = vec![];
+    ) -> crate::Result<Vec<Section>> {
+        let mut section_list: Vec<Section> = self
+            .config
+            .sections
+            .iter()
+            .map(|section| Section {
+                title: section.title.clone(),
+                entries: vec![],
+            })
+            .collect();
 
         let mut commit_hash_map: HashMap<String, bool> = HashMap::new();
 
         for commit in commit_list {
-            let message = commit.message().split("\n").nth(0).unwrap();
             let hash = commit.hash().to_string();
 
-            let re = Regex::new(r"[fix|feat]\(([0-9a-zA-Z_]*)\)").unwrap();
+            // 只有 scope 是当前包，并且类型配置在某个分组中才写入
+            let section_index = match commit.parsed() {
+                Some(parsed) if parsed.has_scope(package) => self.config.section_index(parsed),
+                _ => None,
+            };
 
-            let mut need_insert_message = false;
+            if let Some(section_index) = section_index {
+                if commit_hash_map.contains_key(&hash) {
+                    continue;
+                }
+                let entry = self.get_entry(&commit)?;
+                section_list[section_index].entries.push(entry);
+
+                commit_hash_map.insert(hash, true);
+            }
+        }
+
+        section_list.retain(|section| !section.entries.is_empty());
+
+        Ok(section_list)
+    }
+
+    /**
+     * 生成一个 release，没有要写入的 commit 时返回 None
+     */
+    pub fn gen_release(
+        &mut self,
+        package: &Package,
+        tag: &Tag,
+        commit_list: Vec<Commit>,
+    ) -> crate::Result<Option<Release>> {
+        let version = get_version(&tag.name)?.version;
+        self.build_release(package, tag, version, false, commit_list)
+    }
+
+    /**
+     * 把 head 之后还没有发布的 commit 转化为 Unreleased 的 release
+     * 没有要写入的 commit 时返回 None
+     */
+    pub fn gen_unreleased_release(
+        &mut self,
+        package: &Package,
+        head: &Tag,
+        commit_list: Vec<Commit>,
+    ) -> crate::Result<Option<Release>> {
+        self.build_release(package, head, UNRELEASED.to_owned(), true, commit_list)
+    }
 
-            if re.is_match(message) {
-                if re
-                    .captures(message)
-                    .unwrap()
-                    .index(1)
-                    .to_lowercase()
-                    .eq(package)
+    /**
+     * 把 from...to 之间的 commit 转化为一个 release，tag 见 crate::git::get_commit_ref_range
+     * to 是这个包的 tag 时 version 是它的版本，否则和 gen_unreleased_release 一样是还没有发布的 Unreleased
+     * 没有要写入的 commit 时返回 None
+     */
+    pub fn gen_range_release(
+        &mut self,
+        package: &Package,
+        range: &Tag,
+        to: &str,
+        commit_list: Vec<Commit>,
+    ) -> crate::Result<Option<Release>> {
+        match get_version(to) {
+            Ok(version) if version.package == package.name => {
+                self.build_release(package, range, version.version, false, commit_list)
+            }
+            _ => self.build_release(package, range, UNRELEASED.to_owned(), true, commit_list),
+        }
+    }
+
+    fn build_release(
+        &mut self,
+        package: &Package,
+        tag: &Tag,
+        version: String,
+        unreleased: bool,
+        commit_list: Vec<Commit>,
+    ) -> crate::Result<Option<Release>> {
+        let section_list = self.gen_change_log_by_commit_list(commit_list, &package.scope)?;
+
+        if section_list.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Release {
+            tag: tag.name.clone(),
+            version,
+            date: tag.date_time.clone(),
+            sections: section_list,
+            unreleased,
+        }))
+    }
+
+    /**
+     * 批量获取会写入 changelog 的 pr 的作者，已经缓存的 pr 不会再请求
+     * 之后 get_pr_user_name 直接从缓存中读取，offline 时什么都不做
+     */
+    pub fn prefetch_pull_requests<'a>(
+        &mut self,
+        commit_list: impl IntoIterator<Item = &'a Commit>,
+        package: &str,
+    ) -> crate::Result<()> {
+        if self.config.offline {
+            return Ok(());
+        }
+
+        let mut number_set: BTreeSet<u64> = BTreeSet::new();
+        for commit in commit_list {
+            let pull_request = match commit.parsed() {
+                Some(parsed)
+                    if parsed.has_scope(package) && self.config.section_index(parsed).is_some() =>
                 {
-                    need_insert_message = true
+                    self.forge.pull_request_number(parsed)
+                }
+                _ => None,
+            };
+            if let Some(pull_request) = pull_request {
+                if self.author_cache.get_pull(pull_request, false).is_none() {
+                    number_set.insert(pull_request);
                 }
             }
+        }
 
-            if need_insert_message && !commit_hash_map.get(&hash).is_some() {
-                let md_message = self.get_md_message(&commit);
-                changelog_list.insert(changelog_list.len(), md_message.clone());
+        if number_set.is_empty() {
+            return Ok(());
+        }
 
-                commit_hash_map.insert(hash, true);
+        let number_list: Vec<u64> = number_set.into_iter().collect();
+
+        // pr 的作者只按照 pr 的编号缓存，见 get_pr_user_name
+        let pull_list = self.forge.pull_requests(&number_list)?;
+        for pull_request in &pull_list {
+            self.author_cache
+                .insert_pull(pull_request.number, Some(&pull_request.author));
+        }
+        // 没有返回的编号不是 pr，例如 issue，也缓存下来避免再逐个请求
+        for number in number_list {
+            if !pull_list
+                .iter()
+                .any(|pull_request| pull_request.number == number)
+            {
+                self.author_cache.insert_pull(number, None);
             }
         }
 
-        Ok(changelog_list)
+        Ok(())
     }
 
-    pub fn gen_change_log_to_md(&mut self, tag: &Tag, change_logs: Vec<String>) -> String {
-        let mut md_file_content: String = "".to_owned();
+    /**
+     * 获取所有包最新的 release
+     * 最新的 tag 没有要写入的 commit 或者还没有 tag 时，这个包的 releases 是空的
+     */
+    pub fn latest_changelog(&mut self) -> crate::Result<Changelog> {
+        let mut changelog = Changelog::default();
+
+        for package in self.package_list.clone() {
+            if !crate::git::has_tags(&self.repo, &package.name)? {
+                changelog.packages.push(PackageChangelog {
+                    name: package.name.clone(),
+                    scope: package.scope.clone(),
+                    releases: vec![],
+                });
+                continue;
+            }
+
+            let (tag, commit_list) = crate::git::latest_commits(&self.repo, &package.name)?;
+            self.prefetch_pull_requests(&commit_list, &package.scope)?;
+
+            let release_list = self
+                .gen_release(&package, &tag, commit_list)?
+                .into_iter()
+                .collect();
 
-        md_file_content.push_str(&("## ".to_owned() + tag.name.as_str() + "\n\n"));
-        md_file_content.push_str(format!("`{date_time}`\n\n", date_time = tag.date_time).as_str());
-        for changelog in change_logs {
-            // 格式化成这个样子
-            //  * feat(layout): mix support headerContent render [@chenshuai2144](https://github.com/chenshuai2144)
-            md_file_content.push_str(&("* ".to_owned() + &changelog + "\n"));
+            changelog.packages.push(PackageChangelog {
+                name: package.name.clone(),
+                scope: package.scope.clone(),
+                releases: release_list,
+            });
         }
 
-        md_file_content
+        Ok(changelog)
     }
-    // 获取所有包的change log，会循环一下
-    pub fn get_change_log_list(&mut self) -> Vec<MARKDOWN> {
-        let mut md_packages: Vec<MARKDOWN> = vec![];
-        let package_list = [
-            "utils",
-            "layout",
-            "form",
-            "list",
-            "table",
-            "field",
-            "card",
-            "descriptions",
-        ];
-
-        for package in package_list {
+
+    /**
+     * 获取所有包还没有发布的 commit，从 head 到每个包最新的 tag
+     * 没有新的 commit 或者还没有 tag 时这个包的 releases 是空的
+     */
+    pub fn unreleased_changelog(&mut self, head: &str) -> crate::Result<Changelog> {
+        let mut changelog = Changelog::default();
+
+        for package in self.package_list.clone() {
+            if !crate::git::has_tags(&self.repo, &package.name)? {
+                changelog.packages.push(PackageChangelog {
+                    name: package.name.clone(),
+                    scope: package.scope.clone(),
+                    releases: vec![],
+                });
+                continue;
+            }
+
             let (tag, commit_list) =
-                crate::git::latest_commits(&self.repo, &("@ant-design/pro-".to_owned() + package))
-                    .unwrap();
+                crate::git::unreleased_commits(&self.repo, &package.name, head)?;
+            self.prefetch_pull_requests(&commit_list, &package.scope)?;
+
+            let release_list = self
+                .gen_unreleased_release(&package, &tag, commit_list)?
+                .into_iter()
+                .collect();
+
+            changelog.packages.push(PackageChangelog {
+                name: package.name.clone(),
+                scope: package.scope.clone(),
+                releases: release_list,
+            });
+        }
 
-            let change_logs = self
-                .gen_change_log_by_commit_list(commit_list, package)
-                .expect("生成changelog 失败，请重试");
+        Ok(changelog)
+    }
+
+    /**
+     * 获取任意两个 revspec 之间每个包的改动，例如 hotfix 分支和 master
+     * 从 from 和 to 的 merge base 开始，不需要包有 tag，没有改动的包的 releases 是空的
+     */
+    pub fn range_changelog(&mut self, from: &str, to: &str) -> crate::Result<Changelog> {
+        let mut changelog = Changelog::default();
+
+        // 所有的包共用同一个范围的 commit
+        let (range, commit_list) = crate::git::range_commits(&self.repo, from, to)?;
+
+        for package in self.package_list.clone() {
+            self.prefetch_pull_requests(&commit_list, &package.scope)?;
+
+            let release_list = self
+                .gen_range_release(&package, &range, to, commit_list.clone())?
+                .into_iter()
+                .collect();
+
+            changelog.packages.push(PackageChangelog {
+                name: package.name.clone(),
+                scope: package.scope.clone(),
+                releases: release_list,
+            });
+        }
+
+        Ok(changelog)
+    }
 
-            if change_logs.len() < 1 {
-                // 如果数量不够就直接退出
+    /**
+     * 获取所有包的全部 release
+     * 会遍历所有的标签，还没有 tag 的包的 releases 是空的
+     */
+    pub fn full_changelog(&mut self) -> crate::Result<Changelog> {
+        let mut changelog = Changelog::default();
+
+        for package in self.package_list.clone() {
+            if !crate::git::has_tags(&self.repo, &package.name)? {
+                changelog.packages.push(PackageChangelog {
+                    name: package.name.clone(),
+                    scope: package.scope.clone(),
+                    releases: vec![],
+                });
                 continue;
             }
 
-            let md_file_content = self.gen_change_log_to_md(&tag, change_logs);
+            let commit_and_tag_list = crate::git::full_commits(&self.repo, &package.name)?;
+            // 所有 release 的 pr 一起请求
+            self.prefetch_pull_requests(
+                commit_and_tag_list
+                    .iter()
+                    .flat_map(|commit_and_tag| &commit_and_tag.commit_list),
+                &package.scope,
+            )?;
+
+            let mut release_list: Vec<Release> = vec![];
+            for commit_and_tag in commit_and_tag_list {
+                if let Some(release) =
+                    self.gen_release(&package, &commit_and_tag.tag, commit_and_tag.commit_list)?
+                {
+                    release_list.push(release);
+                }
+            }
+
+            changelog.packages.push(PackageChangelog {
+                name: package.name.clone(),
+                scope: package.scope.clone(),
+                releases: release_list,
+            });
+        }
 
-            md_packages.insert(
-                md_packages.len(),
-                MARKDOWN {
-                    package: package.to_owned(),
-                    content: md_file_content,
-                },
+        Ok(changelog)
+    }
+
+    /**
+     * 用模板把一个包的 release 渲染成 markdown
+     */
+    pub fn render_markdown(&self, package: &PackageChangelog) -> crate::Result<Markdown> {
+        // 格式化成这个样子
+        //  * feat(layout): mix support headerContent render [@chenshuai2144](https://github.com/chenshuai2144)
+        Ok(Markdown {
+            package: package.scope.clone(),
+            content: self.templates.render_package(package)?,
+        })
+    }
+
+    /**
+     * 用 html 模板渲染一个包，options 决定是完整的页面还是片段
+     */
+    pub fn render_html(
+        &self,
+        package: &PackageChangelog,
+        options: &HtmlOptions,
+    ) -> crate::Result<String> {
+        self.html.render_package(package, options)
+    }
+
+    /**
+     * 渲染一个包的 Keep a Changelog 文件
+     * 对比的链接来自 get_all_tag_range，每个 release 对比上一个 tag，Unreleased 对比最新的 tag 和 HEAD
+     * 任意范围的 release 对比 from 和 to，还没有 tag 的包只有这种链接
+     */
+    pub fn render_keep_a_changelog(&self, package: &PackageChangelog) -> crate::Result<String> {
+        let mut links = CompareLinks::default();
+
+        // to 不是 tag 的范围的 release 的 tag 是 from...to
+        for release in &package.releases {
+            if let Some((from, to)) = release.tag.split_once("...") {
+                let url = self.forge.compare_url(from, to);
+                if release.unreleased {
+                    links.unreleased = Some(url);
+                } else {
+                    links.releases.insert(release.tag.clone(), url);
+                }
+            }
+        }
+
+        if !crate::git::has_tags(&self.repo, &package.name)? {
+            return Ok(keep_a_changelog::render(package, &links));
+        }
+
+        if links.unreleased.is_none() {
+            let latest_range = crate::git::get_commit_latest_range(&self.repo, &package.name)?;
+            // Unreleased 对比到读取 commit 的 ref，默认是 HEAD
+            let head = package
+                .releases
+                .iter()
+                .find(|release| release.unreleased)
+                .map_or("HEAD", |release| release.tag.as_str());
+            links.unreleased = Some(
+                self.forge
+                    .compare_url(&latest_range.latest_tag().name, head),
             );
         }
 
-        md_packages
+        for commit_range in crate::git::get_all_tag_range(&self.repo, &package.name)? {
+            if let Some(previous_tag) = commit_range.previous_tag() {
+                let tag = &commit_range.latest_tag().name;
+                links
+                    .releases
+                    .insert(tag.clone(), self.forge.compare_url(previous_tag, tag));
+            }
+        }
+
+        Ok(keep_a_changelog::render(package, &links))
+    }
+
+    /**
+     * 获取所有包的change log，会循环一下
+     * 只包含最新的 tag，没有内容的包会被跳过
+     */
+    pub fn get_change_log_list(&mut self) -> crate::Result<Vec<Markdown>> {
+        let changelog = self.latest_changelog()?;
+
+        changelog
+            .packages
+            .iter()
+            .filter(|package| !package.releases.is_empty())
+            .map(|package| self.render_markdown(package))
+            .collect()
     }
 
     /**
      * 获取所有的changelog
      * 会遍历所有的标签
      */
-    pub fn get_all_change_log_list(&mut self) -> Vec<MARKDOWN> {
-        let mut md_packages: Vec<MARKDOWN> = vec![];
-        let package_list = [
-            "utils",
-            "layout",
-            "form",
-            "list",
-            "table",
-            "field",
-            "card",
-            "descriptions",
-        ];
-        for package in package_list {
-            let mut package_md: Vec<String> = vec![];
-            let commit_and_tag_list =
-                crate::git::full_commits(&self.repo, &("@ant-design/pro-".to_owned() + package))
-                    .unwrap();
+    pub fn get_all_change_log_list(&mut self) -> crate::Result<Vec<Markdown>> {
+        let changelog = self.full_changelog()?;
+
+        changelog
+            .packages
+            .iter()
+            .map(|package| self.render_markdown(package))
+            .collect()
+    }
 
-            for commit_and_tag in commit_and_tag_list {
-                let change_logs = self
-                    .gen_change_log_by_commit_list(commit_and_tag.commit_list, package)
-                    .unwrap();
+    /**
+     * 获取 pr 的作者，按照 pr 的编号缓存用户名
+     * 同一个人的 pr 可能是别人合并的，所以不能按照 commit 的作者缓存
+     * 编号不是 pr 时返回 None，offline 时不请求 forge，见 get_offline_user_name
+     */
+    pub fn get_pr_user_name(
+        &mut self,
+        pr_number: u64,
+        commit: &Commit,
+    ) -> crate::Result<Option<Author>> {
+        if let Some(login) = self.author_cache.get_pull(pr_number, self.config.offline) {
+            return Ok(login.map(|login| self.linked_author(login)));
+        }
 
-                if change_logs.len() < 1 {
-                    // 如果数量不够就直接退出
-                    continue;
-                }
+        if self.config.offline {
+            return Ok(Some(self.get_offline_user_name(commit)));
+        }
 
-                let md_file_content = self.gen_change_log_to_md(&commit_and_tag.tag, change_logs);
+        let login = self.forge.pull_request_author(pr_number)?;
 
-                package_md.insert(package_md.len(), md_file_content);
-            }
+        // 不记录 commit 作者到 pr 作者的映射，pr 的作者可能是帮忙合并的人
+        self.author_cache.insert_pull(pr_number, login.as_deref());
+
+        Ok(login.map(|login| self.linked_author(&login)))
+    }
 
-            md_packages.insert(
-                md_packages.len(),
-                MARKDOWN {
-                    package: package.to_owned(),
-                    content: package_md.join("\n\n"),
-                },
-            )
+    /**
+     * 获取 commit 的作者，按照 commit 的邮箱缓存用户名
+     * 缓存和 noreply 邮箱中都没有时才请求 forge，offline 时见 get_offline_user_name
+     */
+    pub fn get_user_name(&mut self, commit: &Commit) -> crate::Result<Author> {
+        let email = commit.email().as_deref().unwrap_or_default();
+        let name = commit.author().as_deref().unwrap_or_default();
+
+        let known = self.author_cache.get(email, name, false).is_some()
+            || login_from_noreply_email(email).is_some();
+        if !self.config.offline && !email.is_empty() && !known {
+            // 没有账号的邮箱也缓存下来，过期之前不再请求
+            let login = self.forge.commit_author(commit.hash())?;
+            self.author_cache.insert(email, login.as_deref());
         }
-        md_packages
+
+        Ok(self.resolve_author(email, name))
     }
 
     /**
-     * 通过pr的name 获取真实姓名，不让name 和 id 对不上
+     * 不请求 forge 获取 commit 的作者，见 resolve_author
      */
-    pub fn get_pr_user_name(&mut self, pr_number: &str, author: &str) -> String {
-        if self.author_github_map.get(author).is_none() {
-            let pr_url = format!(
-                "{github_url}{repo_name}/pulls/{pr_number}",
-                github_url = " https://api.github.com/repos/",
-                pr_number = pr_number.replace("#", "").trim(),
-                repo_name = self.repo_name,
-            );
-            let body: GithubPull = self
-                .client
-                .get(&pr_url)
-                .header(
-                    "Authorization",
-                    "token ".to_owned() + &env::var("GITHUB_TOKEN").unwrap(),
-                )
-                .header("Accept", "application/vnd.github.v3+json")
-                .send()
-                .unwrap()
-                .json()
-                .unwrap();
-
-            self.author_github_map
-                .insert(author.to_string(), body.user.login);
+    pub fn get_offline_user_name(&self, commit: &Commit) -> Author {
+        self.resolve_author(
+            commit.email().as_deref().unwrap_or_default(),
+            commit.author().as_deref().unwrap_or_default(),
+        )
+    }
+
+    /**
+     * 知道用户名时链接到用户主页，否则只显示作者名
+     */
+    fn resolve_author(&self, email: &str, name: &str) -> Author {
+        match self.resolve_login(email, name) {
+            Some(login) => self.linked_author(&login),
+            None => Author {
+                login: name.to_owned(),
+                url: None,
+            },
+        }
+    }
+
+    fn linked_author(&self, login: &str) -> Author {
+        Author {
+            login: login.to_owned(),
+            url: Some(self.forge.user_url(login)),
         }
+    }
+
+    /**
+     * 不请求 forge 获取用户名，依次尝试
+     * 作者缓存（offline 时包括过期的），noreply 邮箱中的用户名
+     * 作者名不一定是用户名，都没有时返回 None
+     */
+    pub fn resolve_login(&self, email: &str, name: &str) -> Option<String> {
+        self.author_cache
+            .get(email, name, self.config.offline)
+            .flatten()
+            .map(|login| login.to_owned())
+            .or_else(|| login_from_noreply_email(email))
+    }
 
-        // 返回 map 里面对于 name 的映射
-        self.author_github_map.get(author).unwrap().to_string()
+    /**
+     * 把作者缓存写回磁盘，dry run 时不调用
+     */
+    pub fn save_author_cache(&self) -> crate::Result<()> {
+        self.author_cache.save()
     }
+
     /**
-     * 初始化，需要添加项目的地址
+     * 初始化，项目的地址和各种配置都在 config 中
      */
-    pub fn new(repo: String) -> Changelogs {
-        let author_github_map = HashMap::new();
-        let client = Client::new();
-        let repo = Repository::open(repo).unwrap();
-
-        //  仓库的 http 地址，用于生成 commit 的链接
-        let repo_name = repo
-            .find_remote("origin")
-            .unwrap()
-            .url()
-            .unwrap()
-            // git@github.com:ant-design/pro-components.git
-            // -> ant-design/pro-components.git
-            .split(":")
-            .nth(1)
-            .unwrap()
-            .split(".")
-            // ant-design/pro-components.git -> ant-design/pro-components
-            .nth(0)
-            .unwrap()
-            .to_owned();
-
-        let url = format!(
-            "https://api.github.com/repos/{repo_name}",
-            repo_name = repo_name
+    pub fn new(config: Config) -> crate::Result<Changelogs> {
+        let mut author_cache =
+            AuthorCache::load(&config.authors_cache_file(), config.authors_cache_days)?;
+        // authors_file 中的映射优先级最高
+        if let Some(file) = config.authors_file() {
+            let data = fs::read_to_string(&file)?;
+            author_cache.add_overrides(serde_json::from_str(&data).with_context(|_| {
+                crate::ErrorKind::Config {
+                    path: file.display().to_string(),
+                }
+            })?);
+        }
+        let package_list = package::discover(&config)?;
+        let templates = Templates::new(config.template_dir().as_deref())?;
+        let html = HtmlRenderer::new(config.template_dir().as_deref())?;
+        let repo = crate::git::open(&config.path)?;
+
+        let remote_url = remote::find_remote_url(&repo, config.remote.as_deref())?;
+
+        let remote = remote::parse(&remote_url).ok_or_else(|| crate::ErrorKind::Remote {
+            remote: remote_url.clone(),
+        })?;
+        let host = config.host.clone().unwrap_or_else(|| remote.web_host());
+
+        let forge_kind = config.forge.unwrap_or_else(|| ForgeKind::detect(&host));
+        let forge = forge::new_forge(
+            forge_kind,
+            HttpClient::new(config.http_options())?,
+            ForgeRepo {
+                scheme: remote.web_scheme().to_owned(),
+                host,
+                path: remote.path(),
+                api_url: config.api_url.clone(),
+            },
         );
 
-        let body: GithubRepo = client
-            .get(&url)
-            .header(
-                "Authorization",
-                "token ".to_owned() + &env::var("GITHUB_TOKEN").unwrap(),
-            )
-            .header("Accept", "application/vnd.github.v3+json")
-            .send()
-            .unwrap()
-            .json()
-            .unwrap();
-
-        let html_url = body.html_url;
-
-        Changelogs {
+        Ok(Changelogs {
             repo,
-            client: client,
-            author_github_map: author_github_map,
-            github_html_url: html_url,
-            repo_name: repo_name,
-        }
+            config,
+            package_list,
+            templates,
+            html,
+            forge,
+            author_cache,
+        })
+    }
+}
+
+/**
+ * 从 GitHub 的 noreply 邮箱中取出用户名
+ * 12345+chenshuai2144@users.noreply.github.com -> chenshuai2144
+ */
+fn login_from_noreply_email(email: &str) -> Option<String> {
+    let local = email.strip_suffix("@users.noreply.github.com")?;
+    let login = local.split_once('+').map_or(local, |(_, login)| login);
+
+    if login.is_empty() {
+        None
+    } else {
+        Some(login.to_owned())
     }
 }
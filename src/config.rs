@@ -0,0 +1,368 @@
+//! The repository config, `pro-changelog.toml` or `.changelogrc`.
+
+use crate::conventional::ParsedCommit;
+use crate::error::ResultExt;
+use crate::forge::ForgeKind;
+use crate::http::HttpOptions;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Config files looked up in the repository root, in order.
+pub const CONFIG_FILE_LIST: [&str; 2] = ["pro-changelog.toml", ".changelogrc"];
+
+/// Repository specific settings.
+///
+/// Loaded from `pro-changelog.toml` (TOML) or `.changelogrc` (JSON) in the
+/// repository root, every field is optional and falls back to the
+/// [`Default`] value.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Path of the git repository, this is never read from the file.
+    #[serde(skip)]
+    pub path: String,
+    /// Directory the markdown files are written to, relative to the repository.
+    pub out_dir: String,
+    /// Prefix stripped from the npm name to get the commit scope,
+    /// e.g. `@ant-design/pro-`. The package directory name is used when unset.
+    pub scope_prefix: Option<String>,
+    /// Sections of a release in display order, commits of other types are hidden.
+    pub sections: Vec<SectionConfig>,
+    /// The remote used for links and pull request lookups,
+    /// defaults to `upstream` when it exists and `origin` otherwise.
+    pub remote: Option<String>,
+    /// Web host of the repository, defaults to the host of the remote.
+    pub host: Option<String>,
+    /// The forge hosting the repository, guessed from the host when unset.
+    pub forge: Option<ForgeKind>,
+    /// Overrides the API root of the forge, e.g. `https://git.example.com/api/v4`.
+    pub api_url: Option<String>,
+    /// Only handle these packages, by scope or npm name. Empty means all.
+    pub packages: Vec<String>,
+    /// Directory with `release.hbs` and `entry.hbs` replacing the bundled templates,
+    /// relative to the repository.
+    pub template_dir: Option<String>,
+    /// Never call the forge API, logins come from `authors_file` or noreply
+    /// emails, other authors are shown by name.
+    pub offline: bool,
+    /// JSON file mapping commit author names or emails to GitHub logins,
+    /// relative to the repository. These mappings win over the cache.
+    pub authors_file: Option<String>,
+    /// Cache of the logins looked up from the forge, defaults to `.authors.json` in `out_dir`.
+    pub authors_cache: Option<String>,
+    /// Days before a cached login or pull request author is looked up again, 0 never expires.
+    pub authors_cache_days: u64,
+    /// Timeout of a forge or npm registry request, in seconds.
+    pub http_timeout: u64,
+    /// Retries of a request failing with a rate limit, a `5xx` or a timeout.
+    pub http_retries: u32,
+    /// The npm registry checked by `check-publish`.
+    pub npm_registry: String,
+}
+
+/// A section of a release, e.g. `Features` for `feat` commits.
+///
+/// ```toml
+/// [[sections]]
+/// title = "Features"
+/// types = ["feat"]
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct SectionConfig {
+    pub title: String,
+    /// Commit types listed in this section.
+    #[serde(default)]
+    pub types: Vec<String>,
+    /// List every breaking change in this section, whatever its type.
+    #[serde(default)]
+    pub breaking: bool,
+}
+
+impl SectionConfig {
+    fn new(title: &str, types: &[&str]) -> SectionConfig {
+        SectionConfig {
+            title: title.to_owned(),
+            types: types
+                .iter()
+                .map(|commit_type| commit_type.to_string())
+                .collect(),
+            breaking: false,
+        }
+    }
+
+    fn breaking(title: &str) -> SectionConfig {
+        SectionConfig {
+            title: title.to_owned(),
+            types: vec![],
+            breaking: true,
+        }
+    }
+
+    /// Whether the commit belongs to this section.
+    #[must_use]
+    pub fn matches(&self, parsed: &ParsedCommit) -> bool {
+        (self.breaking && parsed.breaking) || self.types.contains(&parsed.commit_type)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            path: ".".to_owned(),
+            out_dir: ".changelog".to_owned(),
+            scope_prefix: None,
+            sections: vec![
+                SectionConfig::breaking("BREAKING CHANGES"),
+                SectionConfig::new("Features", &["feat"]),
+                SectionConfig::new("Bug Fixes", &["fix"]),
+                SectionConfig::new("Performance", &["perf"]),
+            ],
+            remote: None,
+            host: None,
+            forge: None,
+            api_url: None,
+            packages: vec![],
+            template_dir: None,
+            offline: false,
+            authors_file: None,
+            authors_cache: None,
+            authors_cache_days: 30,
+            http_timeout: 30,
+            http_retries: 3,
+            npm_registry: "https://registry.npmjs.org".to_owned(),
+        }
+    }
+}
+
+impl Config {
+    /**
+     * 从仓库根目录读取配置文件，没有配置文件时使用默认配置
+     */
+    pub fn load(path: &str) -> crate::Result<Config> {
+        let file = CONFIG_FILE_LIST
+            .iter()
+            .map(|name| PathBuf::from(path).join(name))
+            .find(|file| file.is_file());
+
+        let mut config = match file {
+            Some(file) => Config::load_file(&file)?,
+            None => Config::default(),
+        };
+        config.path = path.to_owned();
+
+        Ok(config)
+    }
+
+    /// Read a config file, `.toml` files are parsed as TOML and anything else as JSON.
+    pub fn load_file(file: &Path) -> crate::Result<Config> {
+        let data = fs::read_to_string(file)?;
+        let kind = || crate::ErrorKind::Config {
+            path: file.display().to_string(),
+        };
+
+        let config = if file.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&data).with_context(|_| kind())?
+        } else {
+            serde_json::from_str(&data).with_context(|_| kind())?
+        };
+
+        Ok(config)
+    }
+
+    /// The template directory resolved against the repository path.
+    #[must_use]
+    pub fn template_dir(&self) -> Option<PathBuf> {
+        self.template_dir
+            .as_ref()
+            .map(|dir| PathBuf::from(&self.path).join(dir))
+    }
+
+    /// The authors file resolved against the repository path.
+    #[must_use]
+    pub fn authors_file(&self) -> Option<PathBuf> {
+        self.authors_file
+            .as_ref()
+            .map(|file| PathBuf::from(&self.path).join(file))
+    }
+
+    /// The output directory resolved against the repository path.
+    #[must_use]
+    pub fn out_dir(&self) -> PathBuf {
+        PathBuf::from(&self.path).join(&self.out_dir)
+    }
+
+    /// The author cache file, `authors_cache` is relative to the repository.
+    #[must_use]
+    pub fn authors_cache_file(&self) -> PathBuf {
+        match &self.authors_cache {
+            Some(file) => PathBuf::from(&self.path).join(file),
+            None => self.out_dir().join(".authors.json"),
+        }
+    }
+
+    /// The timeouts and retries of the HTTP client.
+    #[must_use]
+    pub fn http_options(&self) -> HttpOptions {
+        HttpOptions {
+            timeout: Duration::from_secs(self.http_timeout),
+            retries: self.http_retries,
+            ..HttpOptions::default()
+        }
+    }
+
+    /// The index of the first section the commit belongs to, `None` when it is hidden.
+    #[must_use]
+    pub fn section_index(&self, parsed: &ParsedCommit) -> Option<usize> {
+        self.sections
+            .iter()
+            .position(|section| section.matches(parsed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "pro-changelog-config-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn load_toml_and_json() {
+        let dir = temp_dir("formats");
+        let toml_file = dir.join("pro-changelog.toml");
+        fs::write(
+            &toml_file,
+            r#"
+out_dir = "docs/changelog"
+scope_prefix = "@ant-design/pro-"
+forge = "gitlab"
+packages = ["layout"]
+
+[[sections]]
+title = "Features"
+types = ["feat"]
+
+[[sections]]
+title = "Breaking"
+breaking = true
+"#,
+        )
+        .unwrap();
+        let json_file = dir.join(".changelogrc");
+        fs::write(
+            &json_file,
+            r#"{"out_dir": "docs/changelog", "scope_prefix": "@ant-design/pro-", "forge": "gitlab",
+                "packages": ["layout"],
+                "sections": [{"title": "Features", "types": ["feat"]}, {"title": "Breaking", "breaking": true}]}"#,
+        )
+        .unwrap();
+
+        for file in [&toml_file, &json_file] {
+            let config = Config::load_file(file).unwrap();
+            assert_eq!(config.out_dir, "docs/changelog", "{}", file.display());
+            assert_eq!(config.scope_prefix.as_deref(), Some("@ant-design/pro-"));
+            assert_eq!(config.forge, Some(ForgeKind::GitLab));
+            assert_eq!(config.packages, vec!["layout"]);
+            let section_list: Vec<_> = config
+                .sections
+                .iter()
+                .map(|section| {
+                    (
+                        section.title.as_str(),
+                        section.types.clone(),
+                        section.breaking,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                section_list,
+                vec![
+                    ("Features", vec!["feat".to_owned()], false),
+                    ("Breaking", vec![], true),
+                ]
+            );
+            // 没有配置的字段使用默认值
+            assert_eq!(config.http_retries, Config::default().http_retries);
+            assert_eq!(config.npm_registry, Config::default().npm_registry);
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn load_prefers_the_toml_file() {
+        let dir = temp_dir("lookup");
+        let path = dir.display().to_string();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.path, path);
+        assert_eq!(config.out_dir, ".changelog");
+
+        fs::write(dir.join(".changelogrc"), r#"{"out_dir": "from-json"}"#).unwrap();
+        assert_eq!(Config::load(&path).unwrap().out_dir, "from-json");
+
+        fs::write(dir.join("pro-changelog.toml"), r#"out_dir = "from-toml""#).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.out_dir, "from-toml");
+        assert_eq!(config.out_dir(), dir.join("from-toml"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_file_is_a_config_error() {
+        let dir = temp_dir("invalid");
+        let file = dir.join("pro-changelog.toml");
+        fs::write(&file, "out_dir = [").unwrap();
+
+        let error = Config::load_file(&file).unwrap_err();
+        assert!(
+            matches!(error.kind(), crate::ErrorKind::Config { path } if *path == file.display().to_string())
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn section_index_follows_the_config_order() {
+        let config = Config::default();
+        let index =
+            |message: &str| config.section_index(&crate::conventional::parse(message).unwrap());
+
+        assert_eq!(index("feat(layout): a"), Some(1));
+        assert_eq!(index("fix(layout): a"), Some(2));
+        assert_eq!(index("perf(layout): a"), Some(3));
+        // 破坏性的改动在第一个 breaking 的 section 中，不管是什么类型
+        assert_eq!(index("feat(layout)!: a"), Some(0));
+        assert_eq!(index("chore(layout): a\n\nBREAKING CHANGE: b"), Some(0));
+        // 没有配置的类型不显示
+        assert_eq!(index("docs(layout): a"), None);
+        assert_eq!(index("chore(layout): a"), None);
+
+        let config = Config {
+            sections: vec![
+                SectionConfig::new("Fixes and docs", &["fix", "docs"]),
+                SectionConfig::new("Features", &["feat"]),
+            ],
+            ..Config::default()
+        };
+        let index =
+            |message: &str| config.section_index(&crate::conventional::parse(message).unwrap());
+        assert_eq!(index("docs: a"), Some(0));
+        assert_eq!(index("feat: a"), Some(1));
+        // 没有 breaking 的 section 时按照类型分组
+        assert_eq!(index("feat!: a"), Some(1));
+    }
+}
@@ -0,0 +1,326 @@
+//! Parsing of [Conventional Commits](https://www.conventionalcommits.org) messages.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// `type(scope1,scope2)!: subject`
+static HEADER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<type>[a-zA-Z]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: *(?P<subject>.+)$")
+        .unwrap()
+});
+
+/// `Token: value` or `Token #value`, `BREAKING CHANGE` is the only token allowed to contain a space.
+static FOOTER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?P<token>BREAKING CHANGE|[a-zA-Z][a-zA-Z-]*)(?::\s*(?P<value>.*)|\s+(?P<issue>#.*))$",
+    )
+    .unwrap()
+});
+
+/// `(#123)` at the end of a squash merged pull request subject.
+static PR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\(#(?P<number>[0-9]+)\)").unwrap());
+
+/// `(!123)` at the end of a GitLab merge request subject.
+static MR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\(!(?P<number>[0-9]+)\)").unwrap());
+
+/// `See merge request group/project!123`, added by GitLab to merge commits.
+static SEE_MR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*See merge request \S*!(?P<number>[0-9]+)\s*$").unwrap());
+
+/// `#123` anywhere in the text.
+static REFERENCE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"#(?P<number>[0-9]+)\b").unwrap());
+
+/// A `Token: value` trailer of a commit message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Footer {
+    pub token: String,
+    pub value: String,
+}
+
+impl Footer {
+    /// Whether the footer marks a breaking change.
+    #[must_use]
+    pub fn is_breaking_change(&self) -> bool {
+        self.token == "BREAKING CHANGE" || self.token == "BREAKING-CHANGE"
+    }
+}
+
+/// A commit message parsed according to the Conventional Commits spec.
+///
+/// See <https://www.conventionalcommits.org/en/v1.0.0/>.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedCommit {
+    /// The commit type, lowercased, e.g. `feat`.
+    pub commit_type: String,
+    /// The scopes, `feat(form,table)` has two.
+    pub scopes: Vec<String>,
+    /// Marked with `!` or a `BREAKING CHANGE` footer.
+    pub breaking: bool,
+    /// The description after the colon.
+    pub subject: String,
+    /// The free text between the header and the footers.
+    pub body: Option<String>,
+    pub footers: Vec<Footer>,
+    /// The pull request the commit was squash merged from, `(#123)` in the subject.
+    pub pull_request: Option<u64>,
+    /// The GitLab merge request, `(!123)` in the subject or a
+    /// `See merge request group/project!123` line. On GitLab `#123` is an issue.
+    pub merge_request: Option<u64>,
+    /// Every `#123` issue or pull request referenced in the subject and footers.
+    pub references: Vec<u64>,
+}
+
+impl ParsedCommit {
+    /// Whether one of the scopes is `scope`, ignoring case.
+    #[must_use]
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(scope))
+    }
+
+    /// The `(name, email)` of every `Co-authored-by: Name <email>` trailer.
+    #[must_use]
+    pub fn co_authors(&self) -> Vec<(String, String)> {
+        self.footer_values("Co-authored-by")
+            .map(|value| match value.split_once('<') {
+                Some((name, email)) => (
+                    name.trim().to_owned(),
+                    email.trim_end_matches('>').trim().to_owned(),
+                ),
+                None => (value.trim().to_owned(), String::new()),
+            })
+            .collect()
+    }
+
+    /// The values of all footers with the given token, ignoring case.
+    pub fn footer_values<'a>(&'a self, token: &'a str) -> impl Iterator<Item = &'a str> {
+        self.footers
+            .iter()
+            .filter(move |footer| footer.token.eq_ignore_ascii_case(token))
+            .map(|footer| footer.value.as_str())
+    }
+}
+
+/**
+ * 解析 commit message，不符合 Conventional Commits 规范的返回 None
+ */
+pub fn parse(message: &str) -> Option<ParsedCommit> {
+    let mut lines = message.lines();
+    let header = lines.next()?.trim();
+    let captures = HEADER_RE.captures(header)?;
+
+    let commit_type = captures["type"].to_lowercase();
+    let scopes = captures
+        .name("scope")
+        .map(|scope| {
+            scope
+                .as_str()
+                .split(',')
+                .map(|name| name.trim().to_owned())
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let subject = captures["subject"].trim().to_owned();
+
+    let line_list = lines.collect::<Vec<_>>();
+    let (body, footers) = parse_body_and_footers(&line_list);
+
+    let breaking =
+        captures.name("breaking").is_some() || footers.iter().any(Footer::is_breaking_change);
+
+    let pull_request = PR_RE
+        .captures_iter(&subject)
+        .last()
+        .and_then(|captures| captures["number"].parse().ok());
+    let rest = line_list.join("\n");
+    let merge_request = MR_RE
+        .captures_iter(&subject)
+        .last()
+        .or_else(|| SEE_MR_RE.captures_iter(&rest).last())
+        .and_then(|captures| captures["number"].parse().ok());
+
+    let mut references: Vec<u64> = vec![];
+    let footer_text = footers.iter().map(|footer| footer.value.as_str());
+    for text in std::iter::once(subject.as_str()).chain(footer_text) {
+        for captures in REFERENCE_RE.captures_iter(text) {
+            if let Ok(number) = captures["number"].parse() {
+                if !references.contains(&number) {
+                    references.push(number);
+                }
+            }
+        }
+    }
+
+    Some(ParsedCommit {
+        commit_type,
+        scopes,
+        breaking,
+        subject,
+        body,
+        footers,
+        pull_request,
+        merge_request,
+        references,
+    })
+}
+
+/// Split the lines after the header into the body and the footers.
+///
+/// The footers start at the first footer line that follows a blank line,
+/// lines that are not footers are appended to the previous footer value.
+fn parse_body_and_footers(lines: &[&str]) -> (Option<String>, Vec<Footer>) {
+    let footer_start = (0..lines.len())
+        .find(|&i| i > 0 && lines[i - 1].trim().is_empty() && FOOTER_RE.is_match(lines[i]))
+        .unwrap_or(lines.len());
+
+    let body = lines[..footer_start].join("\n").trim().to_owned();
+    let body = if body.is_empty() { None } else { Some(body) };
+
+    let mut footers: Vec<Footer> = vec![];
+    for line in &lines[footer_start..] {
+        match FOOTER_RE.captures(line) {
+            Some(captures) => footers.push(Footer {
+                token: captures["token"].to_owned(),
+                value: captures
+                    .name("value")
+                    .or_else(|| captures.name("issue"))
+                    .map_or("", |value| value.as_str())
+                    .trim()
+                    .to_owned(),
+            }),
+            None => {
+                if let Some(footer) = footers.last_mut() {
+                    if !line.trim().is_empty() {
+                        footer.value.push('\n');
+                        footer.value.push_str(line.trim());
+                    }
+                }
+            }
+        }
+    }
+
+    (body, footers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_header() {
+        let parsed = parse("Feat(layout, form): add headerContent (#123)").unwrap();
+
+        assert_eq!(parsed.commit_type, "feat");
+        assert_eq!(parsed.scopes, vec!["layout", "form"]);
+        assert!(parsed.has_scope("Form"));
+        assert!(!parsed.breaking);
+        assert_eq!(parsed.subject, "add headerContent (#123)");
+        assert_eq!(parsed.body, None);
+        assert_eq!(parsed.pull_request, Some(123));
+        assert_eq!(parsed.references, vec![123]);
+    }
+
+    #[test]
+    fn parse_without_scope() {
+        let parsed = parse("chore: release").unwrap();
+
+        assert_eq!(parsed.commit_type, "chore");
+        assert!(parsed.scopes.is_empty());
+        assert_eq!(parsed.pull_request, None);
+    }
+
+    #[test]
+    fn not_conventional() {
+        assert_eq!(parse("Merge branch 'master' into next"), None);
+        assert_eq!(parse("feat(layout) missing colon"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn breaking_change() {
+        assert!(parse("feat(layout)!: drop ie11").unwrap().breaking);
+
+        let parsed = parse("fix(table): reset page\n\nBREAKING CHANGE: page starts at 0").unwrap();
+        assert!(parsed.breaking);
+        assert_eq!(parsed.footers[0].value, "page starts at 0");
+
+        assert!(parse("fix: a\n\nBREAKING-CHANGE: b").unwrap().breaking);
+    }
+
+    #[test]
+    fn body_and_footers() {
+        let message = "fix(form): validate on blur (#7)\n\
+                       \n\
+                       The rules ran on change only.\n\
+                       Second line.\n\
+                       \n\
+                       Closes #5\n\
+                       Co-authored-by: Alice <alice@example.com>\n\
+                       Reviewed-by: Bob\n\
+                       and Carol";
+        let parsed = parse(message).unwrap();
+
+        assert_eq!(
+            parsed.body.as_deref(),
+            Some("The rules ran on change only.\nSecond line.")
+        );
+        assert_eq!(
+            parsed.footers,
+            vec![
+                Footer {
+                    token: "Closes".to_owned(),
+                    value: "#5".to_owned(),
+                },
+                Footer {
+                    token: "Co-authored-by".to_owned(),
+                    value: "Alice <alice@example.com>".to_owned(),
+                },
+                Footer {
+                    token: "Reviewed-by".to_owned(),
+                    value: "Bob\nand Carol".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(parsed.pull_request, Some(7));
+        assert_eq!(parsed.references, vec![7, 5]);
+        assert_eq!(
+            parsed.co_authors(),
+            vec![("Alice".to_owned(), "alice@example.com".to_owned())]
+        );
+    }
+
+    #[test]
+    fn footer_without_blank_line_is_body() {
+        let parsed = parse("fix: a\nCloses #5").unwrap();
+
+        assert_eq!(parsed.body.as_deref(), Some("Closes #5"));
+        assert!(parsed.footers.is_empty());
+    }
+
+    #[test]
+    fn merge_request() {
+        let parsed = parse("fix(form): validate on blur (!42)").unwrap();
+        assert_eq!(parsed.merge_request, Some(42));
+        assert_eq!(parsed.pull_request, None);
+
+        let parsed = parse(
+            "feat(layout): add header (#3)\n\nCloses #3\n\nSee merge request group/sub/project!17",
+        )
+        .unwrap();
+        assert_eq!(parsed.merge_request, Some(17));
+        assert_eq!(parsed.pull_request, Some(3));
+
+        assert_eq!(parse("fix: a (#5)").unwrap().merge_request, None);
+    }
+
+    #[test]
+    fn last_pull_request_wins() {
+        let parsed = parse("fix: revert (#10) again (#12)").unwrap();
+
+        assert_eq!(parsed.pull_request, Some(12));
+        assert_eq!(parsed.references, vec![10, 12]);
+    }
+}
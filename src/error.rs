@@ -1,5 +1,6 @@
-﻿use failure::{Backtrace, Context, Fail};
+﻿use std::error::Error as StdError;
 use std::fmt::{self, Display};
+use std::io;
 use std::result;
 
 /// A specialized [`Result`] type for this crate's operations.
@@ -13,80 +14,188 @@ pub type Result<T> = result::Result<T, Error>;
 
 /// A list enumerating the categories of errors in this crate.
 ///
-/// This list is intended to grow over time and it is not recommended to
-/// exhaustively match against it.
+/// This list is intended to grow over time, it is `#[non_exhaustive]` so
+/// matching on it needs a wildcard arm and adding a variant is not a
+/// breaking change.
 ///
 /// It is used with the [`Error`] struct.
 ///
 /// [`Error`]: std.struct.Error.html
-#[derive(Debug, Fail)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ErrorKind {
     /// An error caused by log failure.
-    #[fail(display = "An error occurred setting up the logger.")]
     Log,
     /// An error caused by Git failure.
-    #[fail(display = "An error occurred using git.")]
     Git,
     /// An error caused by there not being any tags to work from.
-    #[fail(display = "No git tags found.")]
-    NoTags,
+    NoTags {
+        /// The npm name of the package.
+        package: String,
+    },
+    /// An error caused by a tag not following the `package@version` format.
+    TagParse { tag: String },
+    /// An error caused by a git remote that is missing or not a forge url.
+    Remote {
+        /// The remote name or url.
+        remote: String,
+    },
+    /// An error caused by an invalid config file, authors file or `package.json`.
+    Config { path: String },
+    /// An error caused by an invalid or failing template.
+    Template {
+        /// The template name, e.g. `release`.
+        name: String,
+    },
+    /// An error caused by a request that could not be sent or timed out.
+    Network { url: String },
+    /// An error caused by a failed request to GitHub, GitLab, Gitea or Bitbucket.
+    Forge { url: String },
+    /// An error caused by a forge refusing a request without a token.
+    MissingToken {
+        /// The environment variable read for the token, e.g. `GITHUB_TOKEN`.
+        variable: String,
+    },
+    /// An error caused by a failed request to the npm registry.
+    NpmRegistry {
+        /// The package and version, e.g. `@ant-design/pro-layout@6.5.0`.
+        package: String,
+    },
+    /// An error caused by a JSON changelog that cannot be written or read back.
+    Json,
     /// An error caused by a failed filesystem operation.
-    #[fail(display = "An error occured accessing the disk.")]
     Fs,
     /// Any error not part of this list.
-    #[fail(display = "Generic error.")]
     Other,
 }
 
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Log => write!(f, "An error occurred setting up the logger."),
+            ErrorKind::Git => write!(f, "An error occurred using git."),
+            ErrorKind::NoTags { package } => write!(f, "No git tags found for {}.", package),
+            ErrorKind::TagParse { tag } => {
+                write!(f, "The tag `{}` is not `package@version`.", tag)
+            }
+            ErrorKind::Remote { remote } => write!(
+                f,
+                "Cannot use the git remote `{}`, set `remote` or `host` in the config.",
+                remote
+            ),
+            ErrorKind::Config { path } => write!(f, "An error occurred reading {}.", path),
+            ErrorKind::Template { name } => {
+                write!(f, "An error occurred rendering the template {}.", name)
+            }
+            ErrorKind::Network { url } => write!(f, "Could not reach {}.", url),
+            ErrorKind::Forge { url } => {
+                write!(f, "An error occurred calling the forge API at {}.", url)
+            }
+            ErrorKind::MissingToken { variable } => write!(
+                f,
+                "The forge API refused the request, set the {} environment variable.",
+                variable
+            ),
+            ErrorKind::NpmRegistry { package } => write!(
+                f,
+                "An error occurred checking {} in the npm registry.",
+                package
+            ),
+            ErrorKind::Json => write!(f, "An error occurred writing or reading a JSON changelog."),
+            ErrorKind::Fs => write!(f, "An error occured accessing the disk."),
+            ErrorKind::Other => write!(f, "Generic error."),
+        }
+    }
+}
+
 /// A specialized [`Error`] type for this crate's operations.
 ///
+/// The underlying error, if any, is available through [`source`].
+///
 /// [`Error`]: https://doc.rust-lang.org/nightly/std/error/trait.Error.html
+/// [`source`]: https://doc.rust-lang.org/nightly/std/error/trait.Error.html#method.source
 #[derive(Debug)]
 pub struct Error {
-    inner: Context<ErrorKind>,
+    kind: ErrorKind,
+    source: Option<Box<dyn StdError + Send + Sync + 'static>>,
 }
 
 impl Error {
+    /// Create an error of `kind` caused by `source`.
+    pub fn new<E>(kind: ErrorKind, source: E) -> Error
+    where
+        E: Into<Box<dyn StdError + Send + Sync + 'static>>,
+    {
+        Error {
+            kind,
+            source: Some(source.into()),
+        }
+    }
+
     /// Access the [`ErrorKind`] member.
     ///
     /// [`ErrorKind`]: enum.ErrorKind.html
     pub fn kind(&self) -> &ErrorKind {
-        &*self.inner.get_context()
+        &self.kind
     }
 }
 
-impl Fail for Error {
-    fn cause(&self) -> Option<&dyn Fail> {
-        self.inner.cause()
-    }
-
-    fn backtrace(&self) -> Option<&Backtrace> {
-        self.inner.backtrace()
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn StdError + 'static))
     }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        Display::fmt(&self.inner, f)
+        Display::fmt(&self.kind, f)
     }
 }
 
 impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Error {
-        let inner = Context::new(kind);
-        Error { inner }
+        Error { kind, source: None }
     }
 }
 
-impl From<Context<ErrorKind>> for Error {
-    fn from(inner: Context<ErrorKind>) -> Error {
-        Error { inner }
+impl From<io::Error> for Error {
+    fn from(inner: io::Error) -> Error {
+        Error::new(ErrorKind::Fs, inner)
     }
 }
 
-impl From<std::io::Error> for Error {
-    fn from(_inner: std::io::Error) -> Error {
-        let inner = Context::new(crate::ErrorKind::Fs);
-        Error { inner }
+/// Wrap the error of a [`Result`] into an [`Error`] of the given kind,
+/// keeping it as the [`source`].
+///
+/// [`Result`]: https://doc.rust-lang.org/nightly/std/result/enum.Result.html
+/// [`source`]: https://doc.rust-lang.org/nightly/std/error/trait.Error.html#method.source
+pub trait ResultExt<T> {
+    /// Wrap the error into `kind`.
+    fn context(self, kind: ErrorKind) -> Result<T>;
+
+    /// Wrap the error into the kind returned by `f`, which is only called on error.
+    fn with_context<F>(self, f: F) -> Result<T>
+    where
+        F: FnOnce(&dyn StdError) -> ErrorKind;
+}
+
+impl<T, E> ResultExt<T> for result::Result<T, E>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    fn context(self, kind: ErrorKind) -> Result<T> {
+        self.map_err(|error| Error::new(kind, error))
+    }
+
+    fn with_context<F>(self, f: F) -> Result<T>
+    where
+        F: FnOnce(&dyn StdError) -> ErrorKind,
+    {
+        self.map_err(|error| {
+            let kind = f(&error);
+            Error::new(kind, error)
+        })
     }
 }
@@ -0,0 +1,107 @@
+use super::{get_optional_json, token, Forge, ForgeRepo};
+use crate::http::HttpClient;
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct BitbucketUser {
+    nickname: String,
+}
+
+#[derive(Deserialize)]
+struct BitbucketPullRequest {
+    author: BitbucketUser,
+}
+
+#[derive(Deserialize)]
+struct BitbucketCommit {
+    author: BitbucketCommitAuthor,
+}
+
+#[derive(Deserialize)]
+struct BitbucketCommitAuthor {
+    /// Missing when the commit email belongs to no account.
+    user: Option<BitbucketUser>,
+}
+
+/// bitbucket.org, authenticated with the access token in `BITBUCKET_TOKEN`.
+pub struct Bitbucket {
+    client: HttpClient,
+    repo: ForgeRepo,
+    api_url: String,
+}
+
+impl Bitbucket {
+    /// The API root is `repo.api_url` when set.
+    pub fn new(client: HttpClient, repo: ForgeRepo) -> Bitbucket {
+        let api_url = match &repo.api_url {
+            Some(api_url) => api_url.trim_end_matches('/').to_owned(),
+            None => "https://api.bitbucket.org/2.0".to_owned(),
+        };
+
+        Bitbucket {
+            client,
+            repo,
+            api_url,
+        }
+    }
+
+    /// An API request, authenticated when `BITBUCKET_TOKEN` is set.
+    fn get(&self, url: &str) -> RequestBuilder {
+        let request = self.client.get(url);
+        match token("BITBUCKET_TOKEN") {
+            Some(token) => request.header("Authorization", "Bearer ".to_owned() + &token),
+            None => request,
+        }
+    }
+}
+
+impl Forge for Bitbucket {
+    fn repo_url(&self) -> String {
+        self.repo.web_url()
+    }
+
+    fn pull_request_url(&self, number: u64) -> String {
+        format!("{}/pull-requests/{}", self.repo_url(), number)
+    }
+
+    fn commit_url(&self, hash: &str) -> String {
+        format!("{}/commits/{}", self.repo_url(), hash)
+    }
+
+    fn compare_url(&self, from: &str, to: &str) -> String {
+        format!("{}/branches/compare/{}%0D{}", self.repo_url(), to, from)
+    }
+
+    fn user_url(&self, login: &str) -> String {
+        format!("{}/{}/", self.repo.web_root(), login)
+    }
+
+    fn pull_request_author(&self, number: u64) -> crate::Result<Option<String>> {
+        let url = format!(
+            "{api_url}/repositories/{repo_path}/pullrequests/{number}",
+            api_url = self.api_url,
+            repo_path = self.repo.path,
+            number = number,
+        );
+
+        let body: Option<BitbucketPullRequest> =
+            get_optional_json(&self.client, self.get(&url), "BITBUCKET_TOKEN")?;
+        Ok(body.map(|body| body.author.nickname))
+    }
+
+    fn commit_author(&self, hash: &str) -> crate::Result<Option<String>> {
+        let url = format!(
+            "{api_url}/repositories/{repo_path}/commit/{hash}",
+            api_url = self.api_url,
+            repo_path = self.repo.path,
+            hash = hash,
+        );
+
+        let body: Option<BitbucketCommit> =
+            get_optional_json(&self.client, self.get(&url), "BITBUCKET_TOKEN")?;
+        Ok(body
+            .and_then(|body| body.author.user)
+            .map(|user| user.nickname))
+    }
+}
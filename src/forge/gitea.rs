@@ -0,0 +1,100 @@
+use super::{get_optional_json, token, Forge, ForgeRepo};
+use crate::http::HttpClient;
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct GiteaUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaPull {
+    user: GiteaUser,
+}
+
+#[derive(Deserialize)]
+struct GiteaCommit {
+    /// Null when the commit email belongs to no account.
+    author: Option<GiteaUser>,
+}
+
+/// Gitea and Forgejo instances, authenticated with `GITEA_TOKEN`.
+pub struct Gitea {
+    client: HttpClient,
+    repo: ForgeRepo,
+    api_url: String,
+}
+
+impl Gitea {
+    /// The API root is `repo.api_url` when set.
+    pub fn new(client: HttpClient, repo: ForgeRepo) -> Gitea {
+        let api_url = match &repo.api_url {
+            Some(api_url) => api_url.trim_end_matches('/').to_owned(),
+            None => format!("{}/api/v1", repo.web_root()),
+        };
+
+        Gitea {
+            client,
+            repo,
+            api_url,
+        }
+    }
+
+    /// An API request, authenticated when `GITEA_TOKEN` is set.
+    fn get(&self, url: &str) -> RequestBuilder {
+        let request = self.client.get(url);
+        match token("GITEA_TOKEN") {
+            Some(token) => request.header("Authorization", "token ".to_owned() + &token),
+            None => request,
+        }
+    }
+}
+
+impl Forge for Gitea {
+    fn repo_url(&self) -> String {
+        self.repo.web_url()
+    }
+
+    fn pull_request_url(&self, number: u64) -> String {
+        format!("{}/pulls/{}", self.repo_url(), number)
+    }
+
+    fn commit_url(&self, hash: &str) -> String {
+        format!("{}/commit/{}", self.repo_url(), hash)
+    }
+
+    fn compare_url(&self, from: &str, to: &str) -> String {
+        format!("{}/compare/{}...{}", self.repo_url(), from, to)
+    }
+
+    fn user_url(&self, login: &str) -> String {
+        format!("{}/{}", self.repo.web_root(), login)
+    }
+
+    fn pull_request_author(&self, number: u64) -> crate::Result<Option<String>> {
+        let url = format!(
+            "{api_url}/repos/{repo_path}/pulls/{number}",
+            api_url = self.api_url,
+            repo_path = self.repo.path,
+            number = number,
+        );
+
+        let body: Option<GiteaPull> =
+            get_optional_json(&self.client, self.get(&url), "GITEA_TOKEN")?;
+        Ok(body.map(|body| body.user.login))
+    }
+
+    fn commit_author(&self, hash: &str) -> crate::Result<Option<String>> {
+        let url = format!(
+            "{api_url}/repos/{repo_path}/git/commits/{hash}",
+            api_url = self.api_url,
+            repo_path = self.repo.path,
+            hash = hash,
+        );
+
+        let body: Option<GiteaCommit> =
+            get_optional_json(&self.client, self.get(&url), "GITEA_TOKEN")?;
+        Ok(body.and_then(|body| body.author).map(|author| author.login))
+    }
+}
@@ -0,0 +1,313 @@
+use super::{
+    get_json, get_optional_json, pull_requests_one_by_one, token, Forge, ForgeRepo, PullRequest,
+};
+use crate::http::HttpClient;
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+
+/// The number of pull requests fetched by one GraphQL query.
+const GRAPHQL_BATCH_SIZE: usize = 50;
+
+/// The login GitHub shows for deleted accounts.
+const GHOST_LOGIN: &str = "ghost";
+
+#[derive(Deserialize)]
+struct GithubUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GithubPull {
+    user: GithubUser,
+}
+
+#[derive(Deserialize)]
+struct GithubCommit {
+    /// Null when the commit email belongs to no account.
+    author: Option<GithubUser>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlResponse {
+    data: Option<GraphqlData>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlData {
+    /// The aliased pull requests, `pr123`, null when the number does not exist.
+    repository: Option<HashMap<String, Option<GraphqlPull>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphqlPull {
+    number: u64,
+    title: String,
+    merged_at: Option<String>,
+    /// Null for deleted accounts.
+    author: Option<GithubUser>,
+    labels: GraphqlLabels,
+}
+
+#[derive(Deserialize)]
+struct GraphqlLabels {
+    nodes: Vec<GraphqlLabel>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlLabel {
+    name: String,
+}
+
+impl From<GraphqlPull> for PullRequest {
+    fn from(pull: GraphqlPull) -> PullRequest {
+        PullRequest {
+            number: pull.number,
+            author: pull
+                .author
+                .map_or_else(|| GHOST_LOGIN.to_owned(), |author| author.login),
+            title: pull.title,
+            labels: pull
+                .labels
+                .nodes
+                .into_iter()
+                .map(|label| label.name)
+                .collect(),
+            merged_at: pull.merged_at,
+        }
+    }
+}
+
+/// github.com and GitHub Enterprise, authenticated with `GITHUB_TOKEN`.
+pub struct GitHub {
+    client: HttpClient,
+    repo: ForgeRepo,
+    api_url: String,
+}
+
+impl GitHub {
+    /// The API root is `repo.api_url` when set.
+    pub fn new(client: HttpClient, repo: ForgeRepo) -> GitHub {
+        let api_url = match &repo.api_url {
+            Some(api_url) => api_url.trim_end_matches('/').to_owned(),
+            None if repo.host == "github.com" => "https://api.github.com".to_owned(),
+            None => format!("{}/api/v3", repo.web_root()),
+        };
+
+        GitHub {
+            client,
+            repo,
+            api_url,
+        }
+    }
+
+    /// A REST API request, authenticated when `GITHUB_TOKEN` is set.
+    fn get(&self, url: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .get(url)
+            .header("Accept", "application/vnd.github.v3+json");
+        match token("GITHUB_TOKEN") {
+            Some(token) => request.header("Authorization", "token ".to_owned() + &token),
+            None => request,
+        }
+    }
+
+    /// The GraphQL endpoint, `/api/graphql` next to `/api/v3` on GitHub Enterprise.
+    fn graphql_url(&self) -> String {
+        match self.api_url.strip_suffix("/v3") {
+            Some(api_root) => format!("{}/graphql", api_root),
+            None => format!("{}/graphql", self.api_url),
+        }
+    }
+
+    /**
+     * 一次 GraphQL 请求获取多个 pr，每个 pr 用 pr{number} 作为别名
+     */
+    fn graphql_pull_requests(
+        &self,
+        token: &str,
+        numbers: &[u64],
+    ) -> crate::Result<Vec<PullRequest>> {
+        let graphql_url = self.graphql_url();
+        let (owner, name) =
+            self.repo
+                .path
+                .rsplit_once('/')
+                .ok_or_else(|| crate::ErrorKind::Forge {
+                    url: graphql_url.clone(),
+                })?;
+
+        let fields = numbers
+            .iter()
+            .map(|number| {
+                format!(
+                    "pr{number}: pullRequest(number: {number}) {{ number title mergedAt author {{ login }} labels(first: 100) {{ nodes {{ name }} }} }}",
+                    number = number
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let query = format!(
+            "query($owner: String!, $name: String!) {{ repository(owner: $owner, name: $name) {{ {} }} }}",
+            fields
+        );
+
+        let request = self
+            .client
+            .post(&graphql_url)
+            .header("Authorization", "bearer ".to_owned() + token)
+            .json(&json!({
+                "query": query,
+                "variables": { "owner": owner, "name": name },
+            }));
+
+        // 不存在的 pr 会在 errors 中报错，data 中对应的是 null，调用方会把它当作不是 pr
+        let body: GraphqlResponse = get_json(&self.client, request, "GITHUB_TOKEN")?;
+        let pull_list = body
+            .data
+            .and_then(|data| data.repository)
+            .ok_or(crate::ErrorKind::Forge { url: graphql_url })?;
+
+        Ok(pull_list
+            .into_values()
+            .flatten()
+            .map(PullRequest::from)
+            .collect())
+    }
+}
+
+impl Forge for GitHub {
+    fn repo_url(&self) -> String {
+        self.repo.web_url()
+    }
+
+    fn pull_request_url(&self, number: u64) -> String {
+        format!("{}/pull/{}", self.repo_url(), number)
+    }
+
+    fn commit_url(&self, hash: &str) -> String {
+        format!("{}/commit/{}", self.repo_url(), hash)
+    }
+
+    fn compare_url(&self, from: &str, to: &str) -> String {
+        format!("{}/compare/{}...{}", self.repo_url(), from, to)
+    }
+
+    fn user_url(&self, login: &str) -> String {
+        format!("{}/{}", self.repo.web_root(), login)
+    }
+
+    fn pull_request_author(&self, number: u64) -> crate::Result<Option<String>> {
+        let url = format!(
+            "{api_url}/repos/{repo_path}/pulls/{number}",
+            api_url = self.api_url,
+            repo_path = self.repo.path,
+            number = number,
+        );
+
+        let body: Option<GithubPull> =
+            get_optional_json(&self.client, self.get(&url), "GITHUB_TOKEN")?;
+        Ok(body.map(|body| body.user.login))
+    }
+
+    fn commit_author(&self, hash: &str) -> crate::Result<Option<String>> {
+        let url = format!(
+            "{api_url}/repos/{repo_path}/commits/{hash}",
+            api_url = self.api_url,
+            repo_path = self.repo.path,
+            hash = hash,
+        );
+
+        let body: Option<GithubCommit> =
+            get_optional_json(&self.client, self.get(&url), "GITHUB_TOKEN")?;
+        Ok(body.and_then(|body| body.author).map(|author| author.login))
+    }
+
+    /// Uses one GraphQL query per batch, GraphQL needs a token so without
+    /// `GITHUB_TOKEN` the pull requests are looked up one by one.
+    fn pull_requests(&self, numbers: &[u64]) -> crate::Result<Vec<PullRequest>> {
+        let token = match token("GITHUB_TOKEN") {
+            Some(token) => token,
+            None => return pull_requests_one_by_one(self, numbers),
+        };
+
+        let mut pull_list = vec![];
+        for chunk in numbers.chunks(GRAPHQL_BATCH_SIZE) {
+            pull_list.extend(self.graphql_pull_requests(&token, chunk)?);
+        }
+
+        Ok(pull_list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpOptions;
+    use crate::stub_server::{response, StubServer};
+
+    #[test]
+    fn graphql_leaves_out_missing_pull_requests() {
+        let server = StubServer::start(vec![response(
+            200,
+            &[],
+            r#"{
+                "data": {
+                    "repository": {
+                        "pr1": {
+                            "number": 1,
+                            "title": "fix(form): submit twice",
+                            "mergedAt": "2021-10-18T08:00:00Z",
+                            "author": { "login": "alice" },
+                            "labels": { "nodes": [{ "name": "bug" }, { "name": "form" }] }
+                        },
+                        "pr2": null,
+                        "pr3": {
+                            "number": 3,
+                            "title": "docs: typo",
+                            "mergedAt": null,
+                            "author": null,
+                            "labels": { "nodes": [] }
+                        }
+                    }
+                },
+                "errors": [{ "message": "Could not resolve to a PullRequest with the number of 2." }]
+            }"#,
+        )]);
+        let github = GitHub::new(
+            HttpClient::new(HttpOptions::default()).unwrap(),
+            ForgeRepo {
+                scheme: "https".to_owned(),
+                host: "github.com".to_owned(),
+                path: "ant-design/pro-components".to_owned(),
+                api_url: Some(server.url.clone()),
+            },
+        );
+
+        let mut pull_list = github.graphql_pull_requests("token", &[1, 2, 3]).unwrap();
+        pull_list.sort_by_key(|pull_request| pull_request.number);
+
+        let summary: Vec<_> = pull_list
+            .iter()
+            .map(|pull_request| (pull_request.number, pull_request.author.as_str()))
+            .collect();
+        assert_eq!(summary, vec![(1, "alice"), (3, GHOST_LOGIN)]);
+        assert_eq!(pull_list[0].title, "fix(form): submit twice");
+        assert_eq!(pull_list[0].labels, vec!["bug", "form"]);
+        assert_eq!(
+            pull_list[0].merged_at.as_deref(),
+            Some("2021-10-18T08:00:00Z")
+        );
+        assert_eq!(pull_list[1].merged_at, None);
+
+        // 一次请求拿到所有的字段
+        assert_eq!(server.requests().len(), 1);
+        let request = &server.requests()[0];
+        assert!(request.starts_with("POST /graphql "));
+        assert!(request.contains("pr2: pullRequest(number: 2)"));
+        assert!(request.contains("title mergedAt author { login } labels(first: 100)"));
+    }
+}
@@ -0,0 +1,87 @@
+use super::{get_optional_json, token, Forge, ForgeRepo};
+use crate::conventional::ParsedCommit;
+use crate::http::HttpClient;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct GitlabUser {
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct GitlabMergeRequest {
+    author: GitlabUser,
+}
+
+/// gitlab.com and self hosted GitLab, authenticated with `GITLAB_TOKEN`.
+pub struct GitLab {
+    client: HttpClient,
+    repo: ForgeRepo,
+    api_url: String,
+}
+
+impl GitLab {
+    /// The API root is `repo.api_url` when set.
+    pub fn new(client: HttpClient, repo: ForgeRepo) -> GitLab {
+        let api_url = match &repo.api_url {
+            Some(api_url) => api_url.trim_end_matches('/').to_owned(),
+            None => format!("{}/api/v4", repo.web_root()),
+        };
+
+        GitLab {
+            client,
+            repo,
+            api_url,
+        }
+    }
+}
+
+impl Forge for GitLab {
+    fn repo_url(&self) -> String {
+        self.repo.web_url()
+    }
+
+    fn pull_request_url(&self, number: u64) -> String {
+        format!("{}/-/merge_requests/{}", self.repo_url(), number)
+    }
+
+    /// `#123` is an issue on GitLab, merge requests are `!123`.
+    fn pull_request_number(&self, commit: &ParsedCommit) -> Option<u64> {
+        commit.merge_request
+    }
+
+    fn pull_request_reference(&self, number: u64) -> String {
+        format!("!{}", number)
+    }
+
+    fn commit_url(&self, hash: &str) -> String {
+        format!("{}/-/commit/{}", self.repo_url(), hash)
+    }
+
+    fn compare_url(&self, from: &str, to: &str) -> String {
+        format!("{}/-/compare/{}...{}", self.repo_url(), from, to)
+    }
+
+    fn user_url(&self, login: &str) -> String {
+        format!("{}/{}", self.repo.web_root(), login)
+    }
+
+    fn pull_request_author(&self, number: u64) -> crate::Result<Option<String>> {
+        // 项目的 id 可以是 url 编码后的 group/project
+        let url = format!(
+            "{api_url}/projects/{project}/merge_requests/{number}",
+            api_url = self.api_url,
+            project = self.repo.path.replace('/', "%2F"),
+            number = number,
+        );
+
+        let mut request = self.client.get(&url);
+        if let Some(token) = token("GITLAB_TOKEN") {
+            request = request.header("PRIVATE-TOKEN", token);
+        }
+
+        let body: Option<GitlabMergeRequest> =
+            get_optional_json(&self.client, request, "GITLAB_TOKEN")?;
+        Ok(body.map(|body| body.author.username))
+    }
+}
@@ -0,0 +1,420 @@
+//! Links and pull request lookups for the different git hosting services.
+
+mod bitbucket;
+mod gitea;
+mod github;
+mod gitlab;
+
+pub use bitbucket::Bitbucket;
+pub use gitea::Gitea;
+pub use github::GitHub;
+pub use gitlab::GitLab;
+
+use crate::conventional::ParsedCommit;
+use crate::http::{HttpClient, HttpError};
+use reqwest::RequestBuilder;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{env, fmt, str::FromStr};
+
+/// The metadata of a pull request used in the changelog.
+#[derive(Clone, Debug, Default)]
+pub struct PullRequest {
+    pub number: u64,
+    /// The login of the author.
+    pub author: String,
+    pub title: String,
+    pub labels: Vec<String>,
+    /// RFC 3339 timestamp, `None` when it was not merged or not known.
+    pub merged_at: Option<String>,
+}
+
+/// A git hosting service.
+pub trait Forge {
+    /// The web url of the repository, e.g. `https://github.com/ant-design/pro-components`.
+    fn repo_url(&self) -> String;
+
+    /// The web url of a pull request (merge request on GitLab).
+    fn pull_request_url(&self, number: u64) -> String;
+
+    /// The pull request a commit was merged from, `(#123)` in the subject.
+    fn pull_request_number(&self, commit: &ParsedCommit) -> Option<u64> {
+        commit.pull_request
+    }
+
+    /// How a pull request is referred to in text, `#123`.
+    fn pull_request_reference(&self, number: u64) -> String {
+        format!("#{}", number)
+    }
+
+    /// The web url of a commit.
+    fn commit_url(&self, hash: &str) -> String;
+
+    /// The web url of the changes from the revision `from` to `to`, e.g. two tags.
+    fn compare_url(&self, from: &str, to: &str) -> String;
+
+    /// The profile url of a user.
+    fn user_url(&self, login: &str) -> String;
+
+    /// The login of the author of a pull request, this calls the API.
+    ///
+    /// `None` when there is no such pull request, e.g. `(#123)` refers to an issue.
+    fn pull_request_author(&self, number: u64) -> crate::Result<Option<String>>;
+
+    /// The login of the account the commit email belongs to, this calls the API.
+    ///
+    /// `None` when the email has no account, the commit was not pushed, or the
+    /// forge does not link commits to accounts, like GitLab.
+    fn commit_author(&self, _hash: &str) -> crate::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// The metadata of several pull requests, missing ones are left out.
+    ///
+    /// Forges without a batch API look up the authors one by one, the title,
+    /// labels and merge date are left empty.
+    fn pull_requests(&self, numbers: &[u64]) -> crate::Result<Vec<PullRequest>> {
+        pull_requests_one_by_one(self, numbers)
+    }
+}
+
+/// The supported forges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+    Gitea,
+    Bitbucket,
+}
+
+impl ForgeKind {
+    /// Guess the forge from the host of the remote, GitHub when nothing matches.
+    #[must_use]
+    pub fn detect(host: &str) -> ForgeKind {
+        let host = host.to_lowercase();
+        if host.contains("gitlab") {
+            ForgeKind::GitLab
+        } else if host.contains("bitbucket") {
+            ForgeKind::Bitbucket
+        } else if host.contains("gitea") || host.contains("codeberg") {
+            ForgeKind::Gitea
+        } else {
+            ForgeKind::GitHub
+        }
+    }
+}
+
+impl FromStr for ForgeKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<ForgeKind, String> {
+        match name.to_lowercase().as_str() {
+            "github" => Ok(ForgeKind::GitHub),
+            "gitlab" => Ok(ForgeKind::GitLab),
+            "gitea" => Ok(ForgeKind::Gitea),
+            "bitbucket" => Ok(ForgeKind::Bitbucket),
+            _ => Err(format!(
+                "unknown forge `{}`, expected github, gitlab, gitea or bitbucket",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ForgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ForgeKind::GitHub => "github",
+            ForgeKind::GitLab => "gitlab",
+            ForgeKind::Gitea => "gitea",
+            ForgeKind::Bitbucket => "bitbucket",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Where a repository is hosted.
+#[derive(Clone, Debug)]
+pub struct ForgeRepo {
+    /// The scheme of the web interface, `https`, or `http` for a plain http remote.
+    pub scheme: String,
+    /// The web host, e.g. `github.com`.
+    pub host: String,
+    /// The owner and name, e.g. `ant-design/pro-components`.
+    pub path: String,
+    /// Overrides the API root of the forge, used for self hosted instances and stub servers.
+    pub api_url: Option<String>,
+}
+
+impl ForgeRepo {
+    /// The root url of the web interface, e.g. `https://github.com`.
+    #[must_use]
+    pub fn web_root(&self) -> String {
+        format!("{scheme}://{host}", scheme = self.scheme, host = self.host)
+    }
+
+    /// The web url of the repository.
+    #[must_use]
+    pub fn web_url(&self) -> String {
+        format!("{}/{}", self.web_root(), self.path)
+    }
+}
+
+/**
+ * 根据类型创建 forge
+ */
+pub fn new_forge(kind: ForgeKind, client: HttpClient, repo: ForgeRepo) -> Box<dyn Forge> {
+    match kind {
+        ForgeKind::GitHub => Box::new(GitHub::new(client, repo)),
+        ForgeKind::GitLab => Box::new(GitLab::new(client, repo)),
+        ForgeKind::Gitea => Box::new(Gitea::new(client, repo)),
+        ForgeKind::Bitbucket => Box::new(Bitbucket::new(client, repo)),
+    }
+}
+
+/// Read an API token from the environment, an empty variable counts as unset.
+fn token(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|token| !token.is_empty())
+}
+
+/// Look up the authors of pull requests with one `pull_request_author` call each.
+fn pull_requests_one_by_one<F: Forge + ?Sized>(
+    forge: &F,
+    numbers: &[u64],
+) -> crate::Result<Vec<PullRequest>> {
+    let mut pull_list = vec![];
+    for &number in numbers {
+        if let Some(author) = forge.pull_request_author(number)? {
+            pull_list.push(PullRequest {
+                number,
+                author,
+                ..PullRequest::default()
+            });
+        }
+    }
+
+    Ok(pull_list)
+}
+
+/**
+ * 通过共享的 client 发送请求并解析 JSON
+ * 没有 token 时 401 和 403 报 MissingToken，告诉用户应该设置哪个环境变量
+ */
+fn get_json<T: DeserializeOwned>(
+    client: &HttpClient,
+    request: RequestBuilder,
+    token_variable: &str,
+) -> crate::Result<T> {
+    client
+        .get_json(request)
+        .map_err(|error| forge_error(error, token_variable))
+}
+
+/**
+ * 和 get_json 一样，但是 404 返回 None，用于查询可能不存在的 pr 或者 commit
+ * GitHub 查询没有 push 的 commit 时返回 422，也当作不存在
+ */
+fn get_optional_json<T: DeserializeOwned>(
+    client: &HttpClient,
+    request: RequestBuilder,
+    token_variable: &str,
+) -> crate::Result<Option<T>> {
+    match client.get_json(request) {
+        Ok(body) => Ok(Some(body)),
+        Err(error) if matches!(error.status(), Some(404) | Some(422)) => Ok(None),
+        Err(error) => Err(forge_error(error, token_variable)),
+    }
+}
+
+/// The error of a failed forge request, see [`get_json`].
+fn forge_error(error: HttpError, token_variable: &str) -> crate::Error {
+    let kind = match error.status() {
+        Some(401) | Some(403) if token(token_variable).is_none() => {
+            crate::ErrorKind::MissingToken {
+                variable: token_variable.to_owned(),
+            }
+        }
+        _ if error.is_network() => crate::ErrorKind::Network {
+            url: error.url().to_owned(),
+        },
+        _ => crate::ErrorKind::Forge {
+            url: error.url().to_owned(),
+        },
+    };
+    crate::Error::new(kind, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpOptions;
+    use crate::stub_server::{response, StubServer};
+    use std::time::Duration;
+
+    fn stub_forge(kind: ForgeKind, server: &StubServer) -> Box<dyn Forge> {
+        let client = HttpClient::new(HttpOptions {
+            retries: 0,
+            timeout: Duration::from_secs(5),
+            ..HttpOptions::default()
+        })
+        .unwrap();
+
+        new_forge(
+            kind,
+            client,
+            ForgeRepo {
+                scheme: "https".to_owned(),
+                host: "git.example.com".to_owned(),
+                path: "group/repo".to_owned(),
+                api_url: Some(server.url.clone()),
+            },
+        )
+    }
+
+    #[test]
+    fn pull_request_author_of_each_forge() {
+        let case_list = [
+            (
+                ForgeKind::GitHub,
+                r#"{"user": {"login": "alice"}}"#,
+                "GET /repos/group/repo/pulls/7 ",
+            ),
+            (
+                ForgeKind::GitLab,
+                r#"{"author": {"username": "alice"}}"#,
+                "GET /projects/group%2Frepo/merge_requests/7 ",
+            ),
+            (
+                ForgeKind::Gitea,
+                r#"{"user": {"login": "alice"}}"#,
+                "GET /repos/group/repo/pulls/7 ",
+            ),
+            (
+                ForgeKind::Bitbucket,
+                r#"{"author": {"nickname": "alice"}}"#,
+                "GET /repositories/group/repo/pullrequests/7 ",
+            ),
+        ];
+
+        for (kind, body, request) in case_list {
+            let server = StubServer::start(vec![response(200, &[], body)]);
+            let forge = stub_forge(kind, &server);
+
+            assert_eq!(
+                forge.pull_request_author(7).unwrap().as_deref(),
+                Some("alice"),
+                "{}",
+                kind
+            );
+            assert!(server.requests()[0].starts_with(request), "{}", kind);
+        }
+    }
+
+    #[test]
+    fn commit_author_of_each_forge() {
+        let case_list = [
+            (
+                ForgeKind::GitHub,
+                r#"{"author": {"login": "alice"}}"#,
+                r#"{"author": null}"#,
+                "GET /repos/group/repo/commits/abc ",
+            ),
+            (
+                ForgeKind::Gitea,
+                r#"{"author": {"login": "alice"}}"#,
+                r#"{"author": null}"#,
+                "GET /repos/group/repo/git/commits/abc ",
+            ),
+            (
+                ForgeKind::Bitbucket,
+                r#"{"author": {"raw": "Alice <a@example.com>", "user": {"nickname": "alice"}}}"#,
+                r#"{"author": {"raw": "Alice <a@example.com>"}}"#,
+                "GET /repositories/group/repo/commit/abc ",
+            ),
+        ];
+
+        for (kind, linked, unlinked, request) in case_list {
+            let server = StubServer::start(vec![
+                response(200, &[], linked),
+                response(200, &[], unlinked),
+                response(422, &[], r#"{"message": "No commit found"}"#),
+            ]);
+            let forge = stub_forge(kind, &server);
+
+            assert_eq!(
+                forge.commit_author("abc").unwrap().as_deref(),
+                Some("alice"),
+                "{}",
+                kind
+            );
+            assert_eq!(forge.commit_author("abc").unwrap(), None, "{}", kind);
+            assert_eq!(forge.commit_author("abc").unwrap(), None, "{}", kind);
+            assert!(server.requests()[0].starts_with(request), "{}", kind);
+        }
+
+        // GitLab 的 commit 只有邮箱，不请求
+        let server = StubServer::start(vec![]);
+        let forge = stub_forge(ForgeKind::GitLab, &server);
+        assert_eq!(forge.commit_author("abc").unwrap(), None);
+    }
+
+    #[test]
+    fn missing_pull_request_is_none() {
+        for kind in [
+            ForgeKind::GitHub,
+            ForgeKind::GitLab,
+            ForgeKind::Gitea,
+            ForgeKind::Bitbucket,
+        ] {
+            let server = StubServer::start(vec![response(404, &[], r#"{"message": "Not Found"}"#)]);
+            let forge = stub_forge(kind, &server);
+
+            assert_eq!(forge.pull_request_author(7).unwrap(), None, "{}", kind);
+        }
+    }
+
+    #[test]
+    fn gitlab_merge_request_references() {
+        let server = StubServer::start(vec![]);
+        let parsed =
+            crate::conventional::parse("fix(form): a (#3)\n\nSee merge request g/p!17").unwrap();
+
+        let github = stub_forge(ForgeKind::GitHub, &server);
+        assert_eq!(github.pull_request_number(&parsed), Some(3));
+        assert_eq!(github.pull_request_reference(3), "#3");
+
+        let gitlab = stub_forge(ForgeKind::GitLab, &server);
+        assert_eq!(gitlab.pull_request_number(&parsed), Some(17));
+        assert_eq!(gitlab.pull_request_reference(17), "!17");
+    }
+
+    #[test]
+    fn server_error_is_a_forge_error() {
+        let server = StubServer::start(vec![response(500, &[], "")]);
+        let forge = stub_forge(ForgeKind::GitLab, &server);
+
+        let error = forge.pull_request_author(7).unwrap_err();
+        assert!(matches!(error.kind(), crate::ErrorKind::Forge { .. }));
+    }
+
+    #[test]
+    fn pull_requests_one_by_one_leaves_out_missing() {
+        let server = StubServer::start(vec![
+            response(200, &[], r#"{"author": {"username": "alice"}}"#),
+            response(404, &[], "{}"),
+            response(200, &[], r#"{"author": {"username": "bob"}}"#),
+        ]);
+        let forge = stub_forge(ForgeKind::GitLab, &server);
+
+        let pull_list: Vec<_> = forge
+            .pull_requests(&[1, 2, 3])
+            .unwrap()
+            .into_iter()
+            .map(|pull_request| (pull_request.number, pull_request.author))
+            .collect();
+        assert_eq!(
+            pull_list,
+            vec![(1, "alice".to_owned()), (3, "bob".to_owned())]
+        );
+    }
+}
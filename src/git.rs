@@ -1,9 +1,13 @@
-﻿use chrono::prelude::*;
-use failure::ResultExt;
+﻿//! Tags, tag ranges and commits of a package.
+
+use crate::conventional::{self, ParsedCommit};
+use crate::error::ResultExt;
+use chrono::prelude::*;
 use git2::{self, DiffStatsFormat, Repository};
 use semver::Version;
 use std::str;
 
+/// The parts of a `package@version` tag.
 #[derive(Clone, Debug)]
 pub struct TagAndVersion {
     pub package: String,
@@ -26,6 +30,7 @@ impl Tag {
     }
 }
 
+/// A release and its commits.
 #[derive(Clone, Debug)]
 pub struct TagAndCommit {
     pub tag: Tag,
@@ -34,19 +39,39 @@ pub struct TagAndCommit {
 
 /// A commit range for a tagged release
 #[derive(Clone, Debug)]
-pub struct CommitRange<'r> {
+pub(crate) struct CommitRange<'r> {
     latest_tag: Tag,
+    previous_tag: Option<String>,
     start: git2::Commit<'r>,
     end: git2::Commit<'r>,
 }
 
+impl<'r> CommitRange<'r> {
+    /// The tag of the release.
+    #[inline]
+    #[must_use]
+    pub(crate) fn latest_tag(&self) -> &Tag {
+        &self.latest_tag
+    }
+
+    /// The tag the range stops at, `None` for the first release of a package.
+    /// For a [`get_commit_ref_range`] it is the `from` revspec.
+    #[inline]
+    #[must_use]
+    pub(crate) fn previous_tag(&self) -> Option<&str> {
+        self.previous_tag.as_deref()
+    }
+}
+
 /// A git commit.
 #[derive(Clone, Debug)]
 pub struct Commit {
     message: String,
     hash: String,
     author: Option<String>,
+    email: Option<String>,
     datetime: DateTime<Utc>,
+    parsed: Option<ParsedCommit>,
 }
 
 impl Commit {
@@ -71,16 +96,31 @@ impl Commit {
         &self.author
     }
 
+    /// Access the commit author email.
+    #[inline]
+    #[must_use]
+    pub fn email(&self) -> &Option<String> {
+        &self.email
+    }
+
     /// Access the commit datetime.
     #[inline]
     #[must_use]
     pub fn datetime(&self) -> &DateTime<Utc> {
         &self.datetime
     }
+
+    /// Access the Conventional Commits view of the message,
+    /// `None` when the message does not follow the spec.
+    #[inline]
+    #[must_use]
+    pub fn parsed(&self) -> Option<&ParsedCommit> {
+        self.parsed.as_ref()
+    }
 }
 
 /// Diff two git objects.
-pub fn diff(repo: &Repository, o1: git2::Commit, o2: git2::Commit) -> crate::Result<String> {
+pub(crate) fn diff(repo: &Repository, o1: git2::Commit, o2: git2::Commit) -> crate::Result<String> {
     let t1 = o1.tree().context(crate::ErrorKind::Git)?;
     let tree2 = o2.tree().context(crate::ErrorKind::Git)?;
     // If o2 is the first object then we want to include it in the diff
@@ -95,20 +135,32 @@ pub fn diff(repo: &Repository, o1: git2::Commit, o2: git2::Commit) -> crate::Res
     let stats = diff.stats().context(crate::ErrorKind::Git)?;
     let format = DiffStatsFormat::FULL;
     let buf = stats.to_buf(format, 80).context(crate::ErrorKind::Git)?;
-    let buf = str::from_utf8(&*buf).context(crate::ErrorKind::Other)?;
+    let buf = str::from_utf8(&buf).context(crate::ErrorKind::Other)?;
     Ok(buf.to_owned())
 }
 
 /**
- * 获取 tag 和 version
+ * 获取 tag 和 version，不是 package@version 格式的 tag 返回 TagParse
  */
-pub fn get_version(tag: &str) -> TagAndVersion {
-    let package_list = tag.split('@').collect::<Vec<&str>>();
-    let tag = TagAndVersion {
-        package: "@".to_owned() + &package_list.get(1).unwrap().to_string(),
-        version: package_list.last().unwrap().to_string(),
-    };
-    tag
+pub fn get_version(tag: &str) -> crate::Result<TagAndVersion> {
+    // @ant-design/pro-layout@1.0.0 -> (@ant-design/pro-layout, 1.0.0)
+    match tag.rsplit_once('@') {
+        Some((package, version)) if !package.is_empty() && !version.is_empty() => {
+            Ok(TagAndVersion {
+                package: package.to_owned(),
+                version: version.to_owned(),
+            })
+        }
+        _ => Err(crate::ErrorKind::TagParse {
+            tag: tag.to_owned(),
+        }
+        .into()),
+    }
+}
+
+/// Whether the tag is a release of the package.
+fn is_package_tag(tag: &str, package_name: &str) -> bool {
+    get_version(tag).is_ok_and(|version| version.package == package_name)
 }
 
 /**
@@ -117,7 +169,8 @@ pub fn get_version(tag: &str) -> TagAndVersion {
 fn sort_tags<'a>(tags: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
     let mut tags = tags
         .filter_map(|tag| {
-            Version::parse(&get_version(tag).to_owned().version)
+            let version = get_version(tag).ok()?;
+            Version::parse(&version.version)
                 .ok()
                 .map(|version| (tag, version))
         })
@@ -125,238 +178,296 @@ fn sort_tags<'a>(tags: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
 
     tags.sort_by(|(_, a), (_, b)| a.cmp(b));
 
-    let tags = tags.into_iter().map(|(tag, _)| tag).collect();
-
-    tags
+    tags.into_iter().map(|(tag, _)| tag).collect()
 }
 
-fn get_tag_list<'a>(repo: &'a Repository, package_name: &'a str) -> Vec<String> {
-    let mut tag_list = repo
-        .tag_names(None)
-        .context(crate::ErrorKind::Git)
-        .unwrap()
-        .into_iter()
-        .filter_map(|x| x)
-        .filter(|x| x.starts_with(package_name))
-        .filter_map(|tag| {
-            Version::parse(&get_version(tag).to_owned().version)
-                .ok()
-                .map(|version| (tag.to_string(), version))
-        })
-        .collect::<Vec<_>>();
-
-    tag_list.sort_by(|(_, a), (_, b)| a.cmp(b));
-
-    let tags = tag_list
-        .iter()
-        .map(|(tag, _)| tag.clone())
-        .collect::<Vec<String>>();
-
-    tag_list.clear();
-
-    tags
+/// Whether the package has a `package@version` tag, it has none before its first release.
+pub(crate) fn has_tags(repo: &Repository, package_name: &str) -> crate::Result<bool> {
+    Ok(!get_tag_list(repo, package_name)?.is_empty())
 }
 
-/// 获取commit 的范围，默认获取的是 latest
-pub fn get_commit_latest_range<'r>(
-    repo: &'r Repository,
-    package_name: &str,
-) -> crate::Result<CommitRange<'r>> {
+fn get_tag_list(repo: &Repository, package_name: &str) -> crate::Result<Vec<String>> {
     let tag_list = repo.tag_names(None).context(crate::ErrorKind::Git)?;
 
     let tags = sort_tags(
         tag_list
             .into_iter()
-            .filter_map(|x| x)
-            .filter(|x| x.starts_with(package_name)),
+            .flatten()
+            .filter(|tag| is_package_tag(tag, package_name)),
     );
-    let len = tags.len();
 
-    let (start, end) = match len {
-        0 => return Err(crate::ErrorKind::NoTags.into()),
-        1 => (tags.get(len - 1), None),
-        _ => (tags.get(len - 1), tags.get(len - 2)),
-    };
-    // Value has to be `Some()` here.
-    let start_str = start.expect("Tag should have a value.");
-    let (start, end) = match (start_str, end) {
-        (start, None) => {
-            let start = repo.revparse_single(start).context(crate::ErrorKind::Git)?;
+    Ok(tags.into_iter().map(|tag| tag.to_owned()).collect())
+}
+
+/**
+ * 生成一个 release 的 commit 范围，从 start 这个 tag 到上一个 tag end
+ * 没有上一个 tag 时 end 就是 start 本身
+ */
+fn tag_range<'r>(
+    repo: &'r Repository,
+    start_tag: &str,
+    end_tag: Option<&str>,
+) -> crate::Result<CommitRange<'r>> {
+    let start = repo
+        .revparse_single(start_tag)
+        .context(crate::ErrorKind::Git)?;
+    let end = match end_tag {
+        None => {
             let mut reveals = repo.revwalk().context(crate::ErrorKind::Git)?;
             reveals.push(start.id()).context(crate::ErrorKind::Git)?;
             let oid = reveals
-                .nth(0)
+                .next()
                 .ok_or(crate::ErrorKind::Git)?
                 .context(crate::ErrorKind::Git)?;
-            let last = repo.find_object(oid, None).unwrap();
-            (start, last)
+            repo.find_object(oid, None).context(crate::ErrorKind::Git)?
         }
-        (start, Some(end)) => (
-            repo.revparse_single(start).context(crate::ErrorKind::Git)?,
-            repo.revparse_single(end).context(crate::ErrorKind::Git)?,
-        ),
+        Some(end) => repo.revparse_single(end).context(crate::ErrorKind::Git)?,
     };
 
-    let cr = CommitRange {
-        start: start
-            .peel_to_commit()
-            .expect("There's no commit at the start point"),
-        end: end
-            .peel_to_commit()
-            .expect("There's no commit at the end point"),
+    let start = start.peel_to_commit().context(crate::ErrorKind::Git)?;
+    let end = end.peel_to_commit().context(crate::ErrorKind::Git)?;
+
+    Ok(CommitRange {
         latest_tag: Tag {
-            date_time: NaiveDateTime::from_timestamp(
-                start
-                    .peel_to_commit()
-                    .expect("There's no commit at the start point")
-                    .time()
-                    .seconds(),
-                0,
-            )
-            .format("%Y-%m-%d")
-            .to_string(),
-            name: ((*start_str).to_owned()),
+            date_time: NaiveDateTime::from_timestamp(start.time().seconds(), 0)
+                .format("%Y-%m-%d")
+                .to_string(),
+            name: start_tag.to_owned(),
         },
+        previous_tag: end_tag.map(str::to_owned),
+        start,
+        end,
+    })
+}
+
+/// 获取commit 的范围，默认获取的是 latest
+pub(crate) fn get_commit_latest_range<'r>(
+    repo: &'r Repository,
+    package_name: &str,
+) -> crate::Result<CommitRange<'r>> {
+    let tags = get_tag_list(repo, package_name)?;
+    let len = tags.len();
+
+    let (start, end) = match len {
+        0 => {
+            return Err(crate::ErrorKind::NoTags {
+                package: package_name.to_owned(),
+            }
+            .into())
+        }
+        1 => (&tags[len - 1], None),
+        _ => (&tags[len - 1], Some(tags[len - 2].as_str())),
+    };
+
+    tag_range(repo, start, end)
+}
+
+/**
+ * 获取最新的 tag 之后还没有发布的 commit 的范围，从 head 到这个包最新的 tag
+ * head 可以是 HEAD、分支或者任意的 revspec
+ */
+pub(crate) fn get_commit_unreleased_range<'r>(
+    repo: &'r Repository,
+    package_name: &str,
+    head: &str,
+) -> crate::Result<CommitRange<'r>> {
+    let tags = get_tag_list(repo, package_name)?;
+
+    let newest_tag = tags.last().ok_or_else(|| crate::ErrorKind::NoTags {
+        package: package_name.to_owned(),
+    })?;
+
+    tag_range(repo, head, Some(newest_tag))
+}
+
+/**
+ * 任意两个 revspec 之间的范围，例如 tag、分支或者 commit
+ * 从 to 开始，到 from 和 to 的 merge base 结束，分叉的分支只包含 to 这一侧的 commit，和 git diff from...to 一样
+ * to 是 package@version 的 tag 时范围的 tag 就是 to，否则是 from...to
+ */
+pub(crate) fn get_commit_ref_range<'r>(
+    repo: &'r Repository,
+    from: &str,
+    to: &str,
+) -> crate::Result<CommitRange<'r>> {
+    let peel = |spec: &str| {
+        repo.revparse_single(spec)
+            .and_then(|object| object.peel_to_commit())
+            .context(crate::ErrorKind::Git)
     };
+    let from_commit = peel(from)?;
+    let start = peel(to)?;
 
-    Ok(cr)
+    let merge_base = repo
+        .merge_base(from_commit.id(), start.id())
+        .context(crate::ErrorKind::Git)?;
+    let end = repo
+        .find_commit(merge_base)
+        .context(crate::ErrorKind::Git)?;
+
+    // 和 latest、all 生成的 release 同名，--update 时不会重复插入
+    let to_is_tag = repo.find_reference(&format!("refs/tags/{}", to)).is_ok();
+    let name = if to_is_tag && get_version(to).is_ok() {
+        to.to_owned()
+    } else {
+        format!("{}...{}", from, to)
+    };
+
+    Ok(CommitRange {
+        latest_tag: Tag {
+            date_time: NaiveDateTime::from_timestamp(start.time().seconds(), 0)
+                .format("%Y-%m-%d")
+                .to_string(),
+            name,
+        },
+        previous_tag: Some(from.to_owned()),
+        start,
+        end,
+    })
+}
+
+/// Open the git repository at `path`.
+pub(crate) fn open(path: &str) -> crate::Result<Repository> {
+    Repository::open(path).context(crate::ErrorKind::Git)
 }
 
 /// Get the full diff in a single convenience function.
 pub fn latest_diff(path: &str, package_name: &str) -> crate::Result<String> {
-    let repo = Repository::open(path).context(crate::ErrorKind::Git)?;
+    let repo = open(path)?;
     let commit_range = get_commit_latest_range(&repo, package_name)?;
     let start = commit_range.start;
     let end = commit_range.end;
-    Ok(diff(&repo, start, end)?)
+    diff(&repo, start, end)
 }
 
-pub fn get_all_tag_range<'r>(
+/// The commit ranges of every release of a package, newest first.
+///
+/// The first release has no previous tag and is left out.
+pub(crate) fn get_all_tag_range<'r>(
     repo: &'r Repository,
     package_name: &str,
 ) -> crate::Result<Vec<CommitRange<'r>>> {
-    let mut cr_list: Vec<CommitRange> = vec![];
-
-    let tags = get_tag_list(repo, package_name);
+    let tags = get_tag_list(repo, package_name)?;
 
-    let len = tags.len();
-
-    while cr_list.len() < len - 1 {
-        let cr_list_len = cr_list.len();
-        let (start, end) = match len {
-            0 => return Err(crate::ErrorKind::NoTags.into()),
-            1 => (tags.get(len - cr_list_len - 1), None),
-            _ => (
-                tags.get(len - cr_list_len - 1),
-                tags.get(len - cr_list_len - 2),
-            ),
-        };
-
-        // Value has to be `Some()` here.
-        let start_str = start.expect("Tag should have a value.");
-        let (start, end) = match (start_str, end) {
-            (start, None) => {
-                let start = repo.revparse_single(start).context(crate::ErrorKind::Git)?;
-                let mut reveals = repo.revwalk().context(crate::ErrorKind::Git)?;
-                reveals.push(start.id()).context(crate::ErrorKind::Git)?;
-                let oid = reveals
-                    .nth(0)
-                    .ok_or(crate::ErrorKind::Git)?
-                    .context(crate::ErrorKind::Git)?;
-                let last = repo.find_object(oid, None).unwrap();
-                (start, last)
-            }
-            (start, Some(end)) => (
-                repo.revparse_single(start).context(crate::ErrorKind::Git)?,
-                repo.revparse_single(end).context(crate::ErrorKind::Git)?,
-            ),
-        };
-
-        let cr = CommitRange {
-            start: start
-                .peel_to_commit()
-                .expect("There's no commit at the start point"),
-            end: end
-                .peel_to_commit()
-                .expect("There's no commit at the end point"),
-            latest_tag: Tag {
-                date_time: NaiveDateTime::from_timestamp(
-                    start
-                        .peel_to_commit()
-                        .expect("There's no commit at the start point")
-                        .time()
-                        .seconds(),
-                    0,
-                )
-                .format("%Y-%m-%d")
-                .to_string(),
-                name: ((*start_str).to_owned()),
-            },
-        };
-        cr_list.insert(cr_list.len(), cr);
+    if tags.is_empty() {
+        return Err(crate::ErrorKind::NoTags {
+            package: package_name.to_owned(),
+        }
+        .into());
     }
 
-    Ok(cr_list)
+    // 从新到旧，每个 tag 到上一个 tag 是一个 release
+    tags.windows(2)
+        .rev()
+        .map(|pair| tag_range(repo, &pair[1], Some(&pair[0])))
+        .collect()
 }
 
-pub fn get_commit_list_by_commit_range(
+/// The commits of a range, newest first: the commits reachable from the start
+/// but not from the end. The end commit is only included when it is also the
+/// start and the root commit, the first release of a repository with one commit.
+pub(crate) fn get_commit_list_by_commit_range(
     repo: &Repository,
     commit_range: CommitRange,
 ) -> crate::Result<Vec<Commit>> {
     let start = commit_range.start;
     let end = commit_range.end;
 
-    let end_is_first_commit = match end.parent(0) {
-        Err(_err) => true,
-        _ => false,
-    };
+    let is_first_commit = end.id() == start.id() && end.parent(0).is_err();
 
     let mut revwalk = repo.revwalk().context(crate::ErrorKind::Git)?;
     revwalk.push(start.id()).context(crate::ErrorKind::Git)?;
+    // 排除 end 能到达的所有 commit，合并进来的分支中比 end 早的 commit 也不会出现
+    if !is_first_commit {
+        revwalk.hide(end.id()).context(crate::ErrorKind::Git)?;
+    }
     let revwalk = revwalk.filter_map(|id| repo.find_commit(id.ok()?).ok());
 
     let mut commits = vec![];
     for commit in revwalk {
-        if end.id() == commit.id() && !end_is_first_commit {
-            break;
-        }
         let message = commit.message().ok_or(crate::ErrorKind::Git)?.to_string();
 
         let hash = format!("{}", commit.id());
         let author = commit.author().name().map(|name| name.to_owned());
+        let email = commit.author().email().map(|email| email.to_owned());
         let timestamp = commit.time().seconds();
         let naive_datetime = NaiveDateTime::from_timestamp(timestamp, 0);
         let datetime: DateTime<Utc> = DateTime::from_utc(naive_datetime, Utc);
+        let parsed = conventional::parse(&message);
         commits.push(Commit {
             message,
             hash,
             author,
+            email,
             datetime,
+            parsed,
         });
     }
 
     Ok(commits)
 }
 
-/// Get all commits for a path.
-pub fn latest_commits(repo: &Repository, package_name: &str) -> crate::Result<(Tag, Vec<Commit>)> {
-    let commit_range = get_commit_latest_range(&repo, package_name)?;
+/// The commits of a package after its newest tag, up to `head`.
+///
+/// The returned [`Tag`] is named `head` and dated with the `head` commit.
+pub(crate) fn unreleased_commits(
+    repo: &Repository,
+    package_name: &str,
+    head: &str,
+) -> crate::Result<(Tag, Vec<Commit>)> {
+    let commit_range = get_commit_unreleased_range(repo, package_name, head)?;
+
+    let tag = commit_range.latest_tag().clone();
+
+    let commits = get_commit_list_by_commit_range(repo, commit_range)?;
+
+    Ok((tag, commits))
+}
+
+/// The commits between two revspecs, see [`get_commit_ref_range`].
+///
+/// The returned [`Tag`] is named `to` when it is a `package@version` tag and
+/// `from...to` otherwise, it is dated with the `to` commit.
+pub(crate) fn range_commits(
+    repo: &Repository,
+    from: &str,
+    to: &str,
+) -> crate::Result<(Tag, Vec<Commit>)> {
+    let commit_range = get_commit_ref_range(repo, from, to)?;
+
+    let tag = commit_range.latest_tag().clone();
+
+    let commits = get_commit_list_by_commit_range(repo, commit_range)?;
+
+    Ok((tag, commits))
+}
+
+/// The latest tag of a package and the commits since the previous one.
+pub(crate) fn latest_commits(
+    repo: &Repository,
+    package_name: &str,
+) -> crate::Result<(Tag, Vec<Commit>)> {
+    let commit_range = get_commit_latest_range(repo, package_name)?;
 
     let tag = commit_range.clone().latest_tag;
 
-    let commits = get_commit_list_by_commit_range(&repo, commit_range).unwrap();
+    let commits = get_commit_list_by_commit_range(repo, commit_range)?;
 
     Ok((tag, commits))
 }
 
-pub fn full_commits(repo: &Repository, package_name: &str) -> crate::Result<Vec<TagAndCommit>> {
-    let commit_range_list = get_all_tag_range(&repo, package_name)?;
+/// The commits of every release of a package, newest first.
+pub(crate) fn full_commits(
+    repo: &Repository,
+    package_name: &str,
+) -> crate::Result<Vec<TagAndCommit>> {
+    let commit_range_list = get_all_tag_range(repo, package_name)?;
     let mut commit_list: Vec<TagAndCommit> = vec![];
 
     for commit_range in commit_range_list {
         let tag = commit_range.clone().latest_tag;
         // 根据 range 找到 commit
-        let commits = get_commit_list_by_commit_range(&repo, commit_range).unwrap();
+        let commits = get_commit_list_by_commit_range(repo, commit_range)?;
 
         commit_list.insert(
             commit_list.len(),
@@ -369,3 +480,198 @@ pub fn full_commits(repo: &Repository, package_name: &str) -> crate::Result<Vec<
 
     Ok(commit_list)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Signature, Time};
+    use std::{cell::Cell, fs, path::PathBuf};
+
+    /// A throwaway repository, every commit has the empty tree.
+    struct TestRepo {
+        dir: PathBuf,
+        repo: Repository,
+        time: Cell<i64>,
+    }
+
+    impl TestRepo {
+        fn new(name: &str) -> TestRepo {
+            let dir = std::env::temp_dir().join(format!(
+                "pro-changelog-git-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            let repo = Repository::init(&dir).unwrap();
+
+            TestRepo {
+                dir,
+                repo,
+                time: Cell::new(1_600_000_000),
+            }
+        }
+
+        /**
+         * 在 parents 之上提交，每个 commit 比上一个晚一分钟
+         */
+        fn commit(&self, message: &str, parents: &[Oid]) -> Oid {
+            self.time.set(self.time.get() + 60);
+            let signature =
+                Signature::new("Alice", "alice@example.com", &Time::new(self.time.get(), 0))
+                    .unwrap();
+            let tree_id = self.repo.treebuilder(None).unwrap().write().unwrap();
+            let tree = self.repo.find_tree(tree_id).unwrap();
+            let parent_list = parents
+                .iter()
+                .map(|id| self.repo.find_commit(*id).unwrap())
+                .collect::<Vec<_>>();
+            let parent_list = parent_list.iter().collect::<Vec<_>>();
+
+            self.repo
+                .commit(None, &signature, &signature, message, &tree, &parent_list)
+                .unwrap()
+        }
+
+        fn branch(&self, name: &str, id: Oid) {
+            self.repo
+                .reference(&format!("refs/heads/{}", name), id, true, "test")
+                .unwrap();
+        }
+
+        fn tag(&self, name: &str, id: Oid) {
+            let object = self.repo.find_object(id, None).unwrap();
+            self.repo.tag_lightweight(name, &object, false).unwrap();
+        }
+
+        /// The messages of the commits of a range, sorted.
+        fn messages(&self, commit_range: CommitRange) -> Vec<String> {
+            let commit_list = get_commit_list_by_commit_range(&self.repo, commit_range).unwrap();
+            let mut message_list = commit_list
+                .iter()
+                .map(|commit| commit.message().to_owned())
+                .collect::<Vec<_>>();
+            message_list.sort();
+            message_list
+        }
+    }
+
+    impl Drop for TestRepo {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// `init` tagged `pkg@1.0.0`, then `master` and `hotfix` branching off it.
+    fn diverged_repo(name: &str) -> (TestRepo, Oid, Oid) {
+        let test = TestRepo::new(name);
+        let init = test.commit("chore: init", &[]);
+        test.tag("pkg@1.0.0", init);
+        let master = test.commit("feat(pkg): on master", &[init]);
+        test.branch("master", master);
+        let hotfix = test.commit("fix(pkg): on hotfix", &[init]);
+        test.branch("hotfix", hotfix);
+
+        (test, init, hotfix)
+    }
+
+    #[test]
+    fn range_stops_at_the_merge_base() {
+        let (test, init, _) = diverged_repo("merge-base");
+
+        let range = get_commit_ref_range(&test.repo, "master", "hotfix").unwrap();
+        assert_eq!(range.latest_tag().name, "master...hotfix");
+        assert_eq!(range.previous_tag(), Some("master"));
+        assert_eq!(range.end.id(), init);
+        assert_eq!(test.messages(range), vec!["fix(pkg): on hotfix"]);
+
+        // 反过来只有 master 这一侧的 commit
+        let range = get_commit_ref_range(&test.repo, "hotfix", "master").unwrap();
+        assert_eq!(test.messages(range), vec!["feat(pkg): on master"]);
+    }
+
+    #[test]
+    fn range_to_a_package_tag_is_named_by_the_tag() {
+        let (test, _, hotfix) = diverged_repo("range-tag");
+        test.tag("pkg@1.0.1", hotfix);
+
+        let (tag, commit_list) = range_commits(&test.repo, "master", "pkg@1.0.1").unwrap();
+        assert_eq!(tag.name, "pkg@1.0.1");
+        assert_eq!(commit_list.len(), 1);
+        assert_eq!(commit_list[0].message(), "fix(pkg): on hotfix");
+
+        // 分支不是 tag，仍然是 from...to
+        let (tag, _) = range_commits(&test.repo, "pkg@1.0.0", "hotfix").unwrap();
+        assert_eq!(tag.name, "pkg@1.0.0...hotfix");
+    }
+
+    #[test]
+    fn unreleased_commits_after_the_newest_tag() {
+        let test = TestRepo::new("unreleased");
+        let init = test.commit("chore: init", &[]);
+        test.tag("pkg@1.0.0", init);
+        let one = test.commit("feat(pkg): one", &[init]);
+        test.tag("pkg@1.1.0", one);
+        let two = test.commit("fix(pkg): two", &[one]);
+        // 别的包的 tag 不影响
+        test.tag("other@2.0.0", two);
+        let three = test.commit("fix(pkg): three", &[two]);
+        test.branch("master", three);
+
+        let (tag, commit_list) = unreleased_commits(&test.repo, "pkg", "master").unwrap();
+        assert_eq!(tag.name, "master");
+        let message_list: Vec<_> = commit_list.iter().map(Commit::message).collect();
+        assert_eq!(message_list, vec!["fix(pkg): three", "fix(pkg): two"]);
+
+        // 上一个 tag 在根 commit 上，根 commit 属于上一个 release
+        let (tag, commit_list) = latest_commits(&test.repo, "pkg").unwrap();
+        assert_eq!(tag.name, "pkg@1.1.0");
+        let message_list: Vec<_> = commit_list.iter().map(Commit::message).collect();
+        assert_eq!(message_list, vec!["feat(pkg): one"]);
+    }
+
+    #[test]
+    fn no_tags_is_an_error() {
+        let test = TestRepo::new("no-tags");
+        let init = test.commit("chore: init", &[]);
+        test.branch("master", init);
+        test.tag("other@1.0.0", init);
+
+        assert!(!has_tags(&test.repo, "pkg").unwrap());
+        assert!(has_tags(&test.repo, "other").unwrap());
+
+        let error_list = [
+            unreleased_commits(&test.repo, "pkg", "master").unwrap_err(),
+            latest_commits(&test.repo, "pkg").unwrap_err(),
+            full_commits(&test.repo, "pkg").unwrap_err(),
+        ];
+        for error in error_list {
+            assert!(
+                matches!(error.kind(), crate::ErrorKind::NoTags { package } if package == "pkg"),
+                "{:?}",
+                error.kind()
+            );
+        }
+    }
+
+    #[test]
+    fn merged_history_older_than_the_tag() {
+        // feature: init - old - stale
+        // master:  init - base - released (merges old, pkg@1.0.0) - merge (merges stale)
+        let test = TestRepo::new("merge");
+        let init = test.commit("chore: init", &[]);
+        let old = test.commit("feat(pkg): old", &[init]);
+        let stale = test.commit("feat(pkg): stale", &[old]);
+        let base = test.commit("feat(pkg): base", &[init]);
+        let released = test.commit("Merge branch 'feature'", &[base, old]);
+        test.tag("pkg@1.0.0", released);
+        let merge = test.commit("Merge branch 'feature' again", &[released, stale]);
+        test.branch("master", merge);
+
+        // stale 比 tag 早，但是还没有发布；old 和 base 已经在 tag 中了
+        let range = get_commit_unreleased_range(&test.repo, "pkg", "master").unwrap();
+        assert_eq!(
+            test.messages(range),
+            vec!["Merge branch 'feature' again", "feat(pkg): stale"]
+        );
+    }
+}
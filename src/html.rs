@@ -0,0 +1,561 @@
+//! HTML rendering of a package, for docs sites.
+//!
+//! Three templates are used, each can be replaced from the `template_dir` of
+//! the config like the markdown [`template`]s:
+//!
+//! * `html_page.hbs` wraps the releases in a `<div class="changelog">`, and in
+//!   a whole document when [`HtmlOptions::standalone`] is set.
+//! * `html_release.hbs` renders a [`ReleaseContext`], the release, each
+//!   section and each entry have an `id`.
+//! * `html_entry.hbs` renders one [`EntryContext`] inside its `<li>`, it is
+//!   available as the `{{> html_entry}}` partial.
+//!
+//! Values are HTML escaped. The ids are built by [`anchor`], they only
+//! depend on the tag, so links to a release keep working when the page is
+//! generated again. An unreleased release uses the package name instead:
+//!
+//! | element    | id                                              |
+//! |------------|-------------------------------------------------|
+//! | release    | `ant-design-pro-layout-6.5.0`                   |
+//! | unreleased | `ant-design-pro-layout-unreleased`              |
+//! | section    | `ant-design-pro-layout-6.5.0-bug-fixes`         |
+//! | entry      | `ant-design-pro-layout-6.5.0-bug-fixes-1a2b3c4` |
+//!
+//! An id already used on the page gets a `-2`, `-3`… suffix, e.g. two entries
+//! with the same short hash in a section.
+//!
+//! Each `<li>` has `data-type` and `data-scopes` attributes for filtering, and
+//! [`SearchIndex`] maps the scopes and types to the entry ids.
+
+use crate::error::ResultExt;
+use crate::model::{PackageChangelog, Release, UNRELEASED};
+use crate::template::{self, EntryContext, ReleaseContext};
+use handlebars::{handlebars_helper, Handlebars};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+const PAGE_TEMPLATE: &str = include_str!("../templates/html_page.hbs");
+const RELEASE_TEMPLATE: &str = include_str!("../templates/html_release.hbs");
+const ENTRY_TEMPLATE: &str = include_str!("../templates/html_entry.hbs");
+
+/// What [`HtmlRenderer::render_package`] outputs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HtmlOptions {
+    /// A whole document with `<html>` and `<head>`, a fragment to embed otherwise.
+    pub standalone: bool,
+    /// Embed the [`SearchIndex`] as `<script type="application/json" class="changelog-index">`.
+    pub search_index: bool,
+}
+
+/// The entry ids of a package by scope and by type, e.g.
+/// `{"scopes": {"layout": ["ant-design-pro-layout-6.5.0-bug-fixes-1a2b3c4"]}, "types": {"fix": [...]}}`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SearchIndex {
+    pub scopes: BTreeMap<String, Vec<String>>,
+    pub types: BTreeMap<String, Vec<String>>,
+}
+
+impl SearchIndex {
+    /// The index of every entry of the package, newest first.
+    ///
+    /// A breaking change is listed once per section it appears in.
+    #[must_use]
+    pub fn new(package: &PackageChangelog) -> SearchIndex {
+        let mut index = SearchIndex::default();
+
+        for (release, release_ids) in package.releases.iter().zip(page_ids(package)) {
+            for (section, section_ids) in release.sections.iter().zip(release_ids.sections) {
+                for (entry, id) in section.entries.iter().zip(section_ids.entries) {
+                    for scope in &entry.scopes {
+                        index
+                            .scopes
+                            .entry(scope.clone())
+                            .or_default()
+                            .push(id.clone());
+                    }
+                    index
+                        .types
+                        .entry(entry.commit_type.clone())
+                        .or_default()
+                        .push(id);
+                }
+            }
+        }
+
+        index
+    }
+}
+
+/**
+ * release 的 html id，由 tag 生成
+ * 没有发布的 release 的 tag 是 HEAD 之类的 ref，用包名生成，例如 ant-design-pro-layout-unreleased
+ */
+#[must_use]
+pub fn release_id(package: &str, release: &Release) -> String {
+    if release.unreleased {
+        anchor(&[package, UNRELEASED])
+    } else {
+        anchor(&[&release.tag])
+    }
+}
+
+/**
+ * 生成稳定的 html id，只保留小写字母、数字、点、下划线和横线，其他字符换成横线
+ * 例如 `anchor(&["@ant-design/pro-layout@6.5.0", "Bug Fixes"])` 是 `ant-design-pro-layout-6.5.0-bug-fixes`
+ */
+#[must_use]
+pub fn anchor(parts: &[&str]) -> String {
+    let mut id = String::new();
+
+    for c in parts.join("-").to_lowercase().chars() {
+        let c = if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            c
+        } else {
+            '-'
+        };
+        // 连续的横线只保留一个，开头不要横线
+        if c != '-' || !(id.is_empty() || id.ends_with('-')) {
+            id.push(c);
+        }
+    }
+
+    id.trim_end_matches('-').to_owned()
+}
+
+/// The ids of a release and of its sections and entries, see [`page_ids`].
+struct ReleaseIds {
+    id: String,
+    sections: Vec<SectionIds>,
+}
+
+struct SectionIds {
+    id: String,
+    entries: Vec<String>,
+}
+
+/**
+ * 生成一个包的页面中所有的 id，同一个页面中重复的 id 加上 -2、-3 之类的后缀
+ * 例如同一个 section 中 short hash 相同的两个 commit
+ */
+fn page_ids(package: &PackageChangelog) -> Vec<ReleaseIds> {
+    let mut used: HashSet<String> = HashSet::new();
+    let mut unique = |id: String| {
+        let mut unique_id = id.clone();
+        let mut count = 1;
+        while !used.insert(unique_id.clone()) {
+            count += 1;
+            unique_id = format!("{}-{}", id, count);
+        }
+        unique_id
+    };
+
+    let mut release_list = vec![];
+    for release in &package.releases {
+        let release_id = unique(release_id(&package.name, release));
+        let mut section_list = vec![];
+        for section in &release.sections {
+            // entry 的 id 基于没有加后缀的 section id，和 section 的顺序无关
+            let base_id = anchor(&[&release_id, &section.title]);
+            let entries = section
+                .entries
+                .iter()
+                .map(|entry| unique(anchor(&[&base_id, entry.short_hash()])))
+                .collect();
+            section_list.push(SectionIds {
+                id: unique(base_id),
+                entries,
+            });
+        }
+        release_list.push(ReleaseIds {
+            id: release_id,
+            sections: section_list,
+        });
+    }
+
+    release_list
+}
+
+handlebars_helper!(anchor_helper: |*args| {
+    let parts: Vec<String> = args
+        .iter()
+        .map(|arg| match arg.as_str() {
+            Some(text) => text.to_owned(),
+            None => arg.to_string(),
+        })
+        .collect();
+    anchor(&parts.iter().map(String::as_str).collect::<Vec<_>>())
+});
+
+/// The context of `html_release.hbs`, a [`ReleaseContext`] with ids.
+#[derive(Serialize)]
+struct HtmlReleaseContext {
+    /// The id of the release, see [`release_id`].
+    id: String,
+    package: String,
+    tag: String,
+    version: String,
+    date: String,
+    sections: Vec<HtmlSectionContext>,
+    unreleased: bool,
+}
+
+#[derive(Serialize)]
+struct HtmlSectionContext {
+    id: String,
+    title: String,
+    entries: Vec<HtmlEntryContext>,
+}
+
+#[derive(Serialize)]
+struct HtmlEntryContext {
+    id: String,
+    #[serde(flatten)]
+    entry: EntryContext,
+}
+
+impl HtmlReleaseContext {
+    fn new(package: &str, release: &Release, ids: ReleaseIds) -> HtmlReleaseContext {
+        let release = ReleaseContext::new(package, release);
+
+        HtmlReleaseContext {
+            id: ids.id,
+            package: release.package,
+            tag: release.tag,
+            version: release.version,
+            date: release.date,
+            sections: release
+                .sections
+                .into_iter()
+                .zip(ids.sections)
+                .map(|(section, section_ids)| HtmlSectionContext {
+                    id: section_ids.id,
+                    title: section.title,
+                    entries: section
+                        .entries
+                        .into_iter()
+                        .zip(section_ids.entries)
+                        .map(|(entry, id)| HtmlEntryContext { id, entry })
+                        .collect(),
+                })
+                .collect(),
+            unreleased: release.unreleased,
+        }
+    }
+}
+
+/// The context of `html_page.hbs`.
+#[derive(Serialize)]
+struct PageContext<'a> {
+    package: &'a str,
+    scope: &'a str,
+    standalone: bool,
+    /// The rendered releases, inserted without escaping.
+    releases: String,
+    /// The [`SearchIndex`] as JSON, safe inside a `<script>`.
+    index: Option<String>,
+}
+
+/// The registered HTML templates.
+pub struct HtmlRenderer {
+    registry: Handlebars<'static>,
+}
+
+impl HtmlRenderer {
+    /**
+     * 加载 html 模板，template_dir 中存在的模板会覆盖默认模板
+     */
+    pub fn new(template_dir: Option<&Path>) -> crate::Result<HtmlRenderer> {
+        let mut registry = Handlebars::new();
+        registry.set_strict_mode(true);
+        registry.register_helper("anchor", Box::new(anchor_helper));
+
+        for (name, default_template) in [
+            ("html_page", PAGE_TEMPLATE),
+            ("html_release", RELEASE_TEMPLATE),
+            ("html_entry", ENTRY_TEMPLATE),
+        ] {
+            template::register(&mut registry, template_dir, name, default_template)?;
+        }
+
+        Ok(HtmlRenderer { registry })
+    }
+
+    /// Render every release of a package with `html_release.hbs`, wrapped by `html_page.hbs`.
+    pub fn render_package(
+        &self,
+        package: &PackageChangelog,
+        options: &HtmlOptions,
+    ) -> crate::Result<String> {
+        let release_list = package
+            .releases
+            .iter()
+            .zip(page_ids(package))
+            .map(|(release, ids)| {
+                self.render(
+                    "html_release",
+                    &HtmlReleaseContext::new(&package.name, release, ids),
+                )
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        let index = if options.search_index {
+            let json = serde_json::to_string(&SearchIndex::new(package))
+                .context(crate::ErrorKind::Json)?;
+            // 避免 json 中的 </script> 结束标签
+            Some(json.replace("</", "<\\/"))
+        } else {
+            None
+        };
+
+        self.render(
+            "html_page",
+            &PageContext {
+                package: &package.name,
+                scope: &package.scope,
+                standalone: options.standalone,
+                releases: release_list.concat().trim_end().to_owned(),
+                index,
+            },
+        )
+    }
+
+    fn render<T: Serialize>(&self, name: &str, data: &T) -> crate::Result<String> {
+        self.registry
+            .render(name, data)
+            .with_context(|_| crate::ErrorKind::Template {
+                name: name.to_owned(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Author, Entry, Section};
+
+    fn entry(hash: &str, commit_type: &str, scopes: &[&str], subject: &str) -> Entry {
+        Entry {
+            commit_type: commit_type.to_owned(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            subject: subject.to_owned(),
+            message: format!("{}({}): {}", commit_type, scopes.join(","), subject),
+            breaking: false,
+            hash: hash.to_owned(),
+            commit_url: format!("https://github.com/acme/app/commit/{}", hash),
+            pr: None,
+            pr_reference: None,
+            pr_url: None,
+            authors: vec![Author {
+                login: "alice".to_owned(),
+                url: Some("https://github.com/alice".to_owned()),
+            }],
+        }
+    }
+
+    fn release(tag: &str, sections: Vec<(&str, Vec<Entry>)>) -> Release {
+        Release {
+            tag: tag.to_owned(),
+            version: tag.rsplit('@').next().unwrap().to_owned(),
+            date: "2021-10-18".to_owned(),
+            sections: sections
+                .into_iter()
+                .map(|(title, entries)| Section {
+                    title: title.to_owned(),
+                    entries,
+                })
+                .collect(),
+            unreleased: false,
+        }
+    }
+
+    fn package(releases: Vec<Release>) -> PackageChangelog {
+        PackageChangelog {
+            name: "@ant-design/pro-layout".to_owned(),
+            scope: "layout".to_owned(),
+            releases,
+        }
+    }
+
+    fn ids(package: &PackageChangelog) -> Vec<String> {
+        page_ids(package)
+            .into_iter()
+            .flat_map(|release| {
+                std::iter::once(release.id).chain(
+                    release
+                        .sections
+                        .into_iter()
+                        .flat_map(|section| std::iter::once(section.id).chain(section.entries)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn anchors_only_depend_on_the_tag() {
+        assert_eq!(
+            anchor(&["@ant-design/pro-layout@6.5.0", "Bug Fixes"]),
+            "ant-design-pro-layout-6.5.0-bug-fixes"
+        );
+        assert_eq!(anchor(&["--Feat  (form)!", "_v1.0"]), "feat-form-_v1.0");
+
+        let old = release(
+            "@ant-design/pro-layout@6.5.0",
+            vec![(
+                "Bug Fixes",
+                vec![entry("1a2b3c4d", "fix", &["layout"], "a")],
+            )],
+        );
+        let expected = vec![
+            "ant-design-pro-layout-6.5.0",
+            "ant-design-pro-layout-6.5.0-bug-fixes",
+            "ant-design-pro-layout-6.5.0-bug-fixes-1a2b3c4",
+        ];
+        assert_eq!(ids(&package(vec![old.clone()])), expected);
+
+        // 新的 release 不会改变已有的 id
+        let new = release(
+            "@ant-design/pro-layout@6.6.0",
+            vec![(
+                "Bug Fixes",
+                vec![entry("1a2b3c4e", "fix", &["layout"], "b")],
+            )],
+        );
+        assert_eq!(ids(&package(vec![new, old]))[3..], expected);
+
+        // 没有发布的 release 用包名，和读取 commit 的 ref 无关
+        for tag in ["HEAD", "master"] {
+            let unreleased = Release {
+                unreleased: true,
+                ..release(tag, vec![])
+            };
+            assert_eq!(
+                ids(&package(vec![unreleased])),
+                vec!["ant-design-pro-layout-unreleased"]
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_ids_get_a_suffix() {
+        let package = package(vec![release(
+            "@ant-design/pro-layout@6.5.0",
+            vec![
+                (
+                    "Bug Fixes",
+                    vec![
+                        entry("1a2b3c4aaa", "fix", &["layout"], "a"),
+                        entry("1a2b3c4bbb", "fix", &["layout"], "b"),
+                    ],
+                ),
+                (
+                    "Bug fixes!",
+                    vec![entry("1a2b3c4ccc", "fix", &["layout"], "c")],
+                ),
+            ],
+        )]);
+
+        assert_eq!(
+            ids(&package),
+            vec![
+                "ant-design-pro-layout-6.5.0",
+                "ant-design-pro-layout-6.5.0-bug-fixes",
+                "ant-design-pro-layout-6.5.0-bug-fixes-1a2b3c4",
+                "ant-design-pro-layout-6.5.0-bug-fixes-1a2b3c4-2",
+                "ant-design-pro-layout-6.5.0-bug-fixes-2",
+                "ant-design-pro-layout-6.5.0-bug-fixes-1a2b3c4-3",
+            ]
+        );
+
+        let html = HtmlRenderer::new(None)
+            .unwrap()
+            .render_package(&package, &HtmlOptions::default())
+            .unwrap();
+        for id in ids(&package) {
+            assert_eq!(html.matches(&format!("id=\"{}\"", id)).count(), 1, "{}", id);
+        }
+    }
+
+    #[test]
+    fn values_are_escaped() {
+        let mut entry = entry("1a2b3c4d", "fix", &["layout"], "");
+        entry.message = r#"fix: <script>alert("x")</script> & co"#.to_owned();
+        entry.authors = vec![Author {
+            login: "O'Brien <ob>".to_owned(),
+            url: None,
+        }];
+        let package = package(vec![release(
+            "@ant-design/pro-layout@6.5.0",
+            vec![("Bug Fixes", vec![entry])],
+        )]);
+
+        let html = HtmlRenderer::new(None)
+            .unwrap()
+            .render_package(&package, &HtmlOptions::default())
+            .unwrap();
+        assert!(!html.contains("<script>"), "{}", html);
+        assert!(html.contains("fix: &lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt; &amp; co"));
+        assert!(html.contains(r#"<span class="author">O&#x27;Brien &lt;ob&gt;</span>"#));
+    }
+
+    #[test]
+    fn search_index_by_scope_and_type() {
+        let package = package(vec![release(
+            "@ant-design/pro-layout@6.5.0",
+            vec![
+                (
+                    "Features",
+                    vec![entry("2b2b2b2b", "feat", &["layout"], "b")],
+                ),
+                (
+                    "Bug Fixes",
+                    vec![entry("1a1a1a1a", "fix", &["layout", "</script>"], "a")],
+                ),
+            ],
+        )]);
+        let feat_id = "ant-design-pro-layout-6.5.0-features-2b2b2b2";
+        let fix_id = "ant-design-pro-layout-6.5.0-bug-fixes-1a1a1a1";
+
+        let index = SearchIndex::new(&package);
+        assert_eq!(
+            index.scopes,
+            BTreeMap::from([
+                ("</script>".to_owned(), vec![fix_id.to_owned()]),
+                (
+                    "layout".to_owned(),
+                    vec![feat_id.to_owned(), fix_id.to_owned()]
+                ),
+            ])
+        );
+        assert_eq!(
+            index.types,
+            BTreeMap::from([
+                ("feat".to_owned(), vec![feat_id.to_owned()]),
+                ("fix".to_owned(), vec![fix_id.to_owned()]),
+            ])
+        );
+
+        let renderer = HtmlRenderer::new(None).unwrap();
+        let html = renderer
+            .render_package(
+                &package,
+                &HtmlOptions {
+                    search_index: true,
+                    ..HtmlOptions::default()
+                },
+            )
+            .unwrap();
+        let script = html
+            .split_once(r#"<script type="application/json" class="changelog-index">"#)
+            .and_then(|(_, rest)| rest.split_once("</script>"))
+            .map(|(json, _)| json)
+            .unwrap();
+        let embedded: serde_json::Value = serde_json::from_str(script).unwrap();
+        assert_eq!(embedded, serde_json::to_value(&index).unwrap());
+
+        let html = renderer
+            .render_package(&package, &HtmlOptions::default())
+            .unwrap();
+        assert!(!html.contains("changelog-index"));
+    }
+}
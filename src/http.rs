@@ -0,0 +1,364 @@
+//! The HTTP client shared by the forge backends and the npm registry check.
+//!
+//! Failed requests are retried with an exponential backoff and jitter:
+//!
+//! * Rate limits, `429` or `403` with `X-RateLimit-Remaining: 0`, wait for
+//!   `Retry-After` or `X-RateLimit-Reset` and are retried for every method
+//!   since the server did not handle the request. A wait longer than
+//!   [`HttpOptions::max_wait`] fails right away with [`HttpError::RateLimited`].
+//! * `5xx` responses, timeouts and connection errors are only retried for
+//!   `GET` and `HEAD`.
+
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Client, Method, RequestBuilder, Response, StatusCode,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::hash_map::RandomState,
+    error::Error,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const RATE_LIMIT_REMAINING: &str = "x-ratelimit-remaining";
+const RATE_LIMIT_RESET: &str = "x-ratelimit-reset";
+
+/// Timeouts and retry settings of an [`HttpClient`].
+#[derive(Clone, Debug)]
+pub struct HttpOptions {
+    /// Timeout of a single request, connecting and reading the body included.
+    pub timeout: Duration,
+    /// Retries after the first attempt, 0 disables retrying.
+    pub retries: u32,
+    /// The first backoff, doubled after every attempt.
+    pub backoff: Duration,
+    /// The longest wait before a retry, for rate limits and backoffs alike.
+    pub max_wait: Duration,
+}
+
+impl Default for HttpOptions {
+    fn default() -> HttpOptions {
+        HttpOptions {
+            timeout: Duration::from_secs(30),
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            max_wait: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A failed request, after the retries.
+#[derive(Debug)]
+pub enum HttpError {
+    /// The request did not finish within [`HttpOptions::timeout`].
+    Timeout { url: String, error: reqwest::Error },
+    /// The server asked to wait longer than [`HttpOptions::max_wait`], or
+    /// kept rate limiting until the retries ran out.
+    RateLimited { url: String, retry_after: u64 },
+    /// The server answered with an error status.
+    Status { url: String, status: u16 },
+    /// The request could not be sent, e.g. the host is unreachable.
+    Request { url: String, error: reqwest::Error },
+    /// The response body is not the expected JSON.
+    Decode { url: String, error: reqwest::Error },
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HttpError::Timeout { url, .. } => write!(f, "Request to {} timed out.", url),
+            HttpError::RateLimited { url, retry_after } => write!(
+                f,
+                "Request to {} was rate limited, retry after {} seconds.",
+                url, retry_after
+            ),
+            HttpError::Status { url, status } => {
+                write!(f, "Request to {} failed with status {}.", url, status)
+            }
+            HttpError::Request { url, .. } => write!(f, "Request to {} failed.", url),
+            HttpError::Decode { url, .. } => write!(f, "Invalid response from {}.", url),
+        }
+    }
+}
+
+impl Error for HttpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HttpError::Timeout { error, .. }
+            | HttpError::Request { error, .. }
+            | HttpError::Decode { error, .. } => Some(error),
+            HttpError::RateLimited { .. } | HttpError::Status { .. } => None,
+        }
+    }
+}
+
+impl HttpError {
+    /// The url of the failed request.
+    #[must_use]
+    pub fn url(&self) -> &str {
+        match self {
+            HttpError::Timeout { url, .. }
+            | HttpError::RateLimited { url, .. }
+            | HttpError::Status { url, .. }
+            | HttpError::Request { url, .. }
+            | HttpError::Decode { url, .. } => url,
+        }
+    }
+
+    /// Whether the server was never reached or did not answer in time.
+    #[must_use]
+    pub fn is_network(&self) -> bool {
+        matches!(self, HttpError::Timeout { .. } | HttpError::Request { .. })
+    }
+
+    /// The status of the response, `None` when there was no response.
+    #[must_use]
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            HttpError::Status { status, .. } => Some(*status),
+            HttpError::RateLimited { .. } => Some(StatusCode::TOO_MANY_REQUESTS.as_u16()),
+            _ => None,
+        }
+    }
+}
+
+/// A [`Client`] with timeouts, retries and typed errors.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client,
+    options: HttpOptions,
+}
+
+impl HttpClient {
+    /**
+     * 创建 client，timeout 对每一次请求都生效
+     */
+    pub fn new(options: HttpOptions) -> crate::Result<HttpClient> {
+        use crate::error::ResultExt;
+
+        let client = Client::builder()
+            .timeout(options.timeout)
+            .build()
+            .context(crate::ErrorKind::Other)?;
+
+        Ok(HttpClient { client, options })
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /**
+     * 发送请求，遇到限流、5xx 和超时会重试，返回成功的 response
+     */
+    pub fn send(&self, request: RequestBuilder) -> Result<Response, HttpError> {
+        let (method, url) = match request.try_clone().map(RequestBuilder::build) {
+            Some(Ok(built)) => (built.method().clone(), built.url().to_string()),
+            // body 是流的请求不能重试
+            _ => (Method::POST, String::new()),
+        };
+        let idempotent = method == Method::GET || method == Method::HEAD;
+
+        let mut request = request;
+        let mut attempt = 0;
+        loop {
+            let retry_request = if attempt < self.options.retries {
+                request.try_clone()
+            } else {
+                None
+            };
+
+            let (error, wait) = match request.send() {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    if is_rate_limited(status, response.headers()) {
+                        let wait = retry_after(response.headers())
+                            .unwrap_or_else(|| self.backoff(attempt));
+                        let error = HttpError::RateLimited {
+                            url: url.clone(),
+                            retry_after: wait.as_secs(),
+                        };
+                        // 等太久就直接报错
+                        if wait > self.options.max_wait {
+                            return Err(error);
+                        }
+                        (error, wait)
+                    } else {
+                        let error = HttpError::Status {
+                            url: url.clone(),
+                            status: status.as_u16(),
+                        };
+                        if !(status.is_server_error() && idempotent) {
+                            return Err(error);
+                        }
+                        (error, self.backoff(attempt))
+                    }
+                }
+                Err(error) => {
+                    let error = if error.is_timeout() {
+                        HttpError::Timeout {
+                            url: url.clone(),
+                            error,
+                        }
+                    } else {
+                        HttpError::Request {
+                            url: url.clone(),
+                            error,
+                        }
+                    };
+                    if !idempotent {
+                        return Err(error);
+                    }
+                    (error, self.backoff(attempt))
+                }
+            };
+
+            request = match retry_request {
+                Some(retry_request) => retry_request,
+                None => return Err(error),
+            };
+            thread::sleep(wait);
+            attempt += 1;
+        }
+    }
+
+    /// Send a request and decode the JSON body.
+    pub fn get_json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, HttpError> {
+        let mut response = self.send(request)?;
+        response.json().map_err(|error| HttpError::Decode {
+            url: response.url().to_string(),
+            error,
+        })
+    }
+
+    /// The backoff before retry `attempt + 1`, a random duration between half
+    /// and all of `backoff * 2^attempt`, capped at `max_wait`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .options
+            .backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .map_or(self.options.max_wait, |backoff| {
+                backoff.min(self.options.max_wait)
+            });
+
+        let half = backoff / 2;
+        let jitter_nanos = half.as_nanos() as u64;
+        if jitter_nanos == 0 {
+            return backoff;
+        }
+        half + Duration::from_nanos(random() % jitter_nanos)
+    }
+}
+
+/// GitHub answers a rate limit with `403` and no remaining requests, other forges with `429`.
+fn is_rate_limited(status: StatusCode, headers: &HeaderMap) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN
+            && (header_u64(headers, RATE_LIMIT_REMAINING) == Some(0)
+                || headers.contains_key(RETRY_AFTER)))
+}
+
+/// The wait asked for by `Retry-After` in seconds, or until the `X-RateLimit-Reset` timestamp.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(seconds) = header_u64(headers, RETRY_AFTER.as_str()) {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let reset = header_u64(headers, RATE_LIMIT_RESET)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(reset.saturating_sub(now)))
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// A random number for the jitter, the std hasher is randomly seeded.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{response, StubServer};
+
+    fn client() -> HttpClient {
+        HttpClient::new(HttpOptions {
+            timeout: Duration::from_secs(5),
+            retries: 2,
+            backoff: Duration::from_millis(1),
+            max_wait: Duration::from_secs(1),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn retries_after_rate_limit() {
+        let server = StubServer::start(vec![
+            response(429, &[("Retry-After", "0")], ""),
+            response(200, &[], "{}"),
+        ]);
+
+        let client = client();
+        assert!(client.send(client.post(&server.url)).is_ok());
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn fails_when_rate_limit_wait_is_too_long() {
+        let server = StubServer::start(vec![response(429, &[("Retry-After", "120")], "")]);
+
+        let client = client();
+        match client.send(client.get(&server.url)) {
+            Err(HttpError::RateLimited { retry_after, .. }) => assert_eq!(retry_after, 120),
+            other => panic!("expected a rate limit error, got {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn retries_get_on_server_error() {
+        let server = StubServer::start(vec![
+            response(503, &[], ""),
+            response(502, &[], ""),
+            response(200, &[], r#"{"ok":true}"#),
+        ]);
+
+        let client = client();
+        let body: serde_json::Value = client.get_json(client.get(&server.url)).unwrap();
+        assert_eq!(body["ok"], true);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn does_not_retry_post_on_server_error() {
+        let server = StubServer::start(vec![response(500, &[], ""), response(200, &[], "{}")]);
+
+        let client = client();
+        let error = client
+            .send(client.post(&server.url).body("{}"))
+            .unwrap_err();
+        assert_eq!(error.status(), Some(500));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        let server = StubServer::start(vec![response(404, &[], ""), response(200, &[], "{}")]);
+
+        let client = client();
+        let error = client.send(client.get(&server.url)).unwrap_err();
+        assert_eq!(error.status(), Some(404));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
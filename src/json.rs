@@ -0,0 +1,330 @@
+//! JSON export of the [`model`](crate::model), for docs sites and release bots.
+//!
+//! The document is the [`Changelog`] with a `schema_version` field:
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "packages": [
+//!     {
+//!       "name": "@ant-design/pro-layout",
+//!       "scope": "layout",
+//!       "releases": [
+//!         {
+//!           "tag": "@ant-design/pro-layout@6.5.0",
+//!           "version": "6.5.0",
+//!           "date": "2020-09-01",
+//!           "sections": [{ "title": "Features", "entries": [] }]
+//!         }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! The JSON Schema is [`SCHEMA`], also shipped as `schema/changelog-v1.schema.json`
+//! in the crate. Adding a field keeps the version, renaming or removing one bumps it.
+
+use crate::error::ResultExt;
+use crate::model::Changelog;
+use serde::{Deserialize, Serialize};
+
+/// The version of the JSON document written by [`to_string`].
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// The JSON Schema of version [`SCHEMA_VERSION`].
+pub const SCHEMA: &str = include_str!("../schema/changelog-v1.schema.json");
+
+/// The serialized document, the changelog tagged with its schema version.
+#[derive(Serialize, Deserialize)]
+struct Document {
+    schema_version: u64,
+    #[serde(flatten)]
+    changelog: Changelog,
+}
+
+/**
+ * 把 changelog 序列化成带 schema_version 的 json，末尾有换行
+ */
+pub fn to_string(changelog: &Changelog) -> crate::Result<String> {
+    let document = Document {
+        schema_version: SCHEMA_VERSION,
+        changelog: changelog.clone(),
+    };
+
+    let mut json = serde_json::to_string_pretty(&document).context(crate::ErrorKind::Json)?;
+    json.push('\n');
+
+    Ok(json)
+}
+
+/**
+ * 读取 to_string 生成的 json，schema_version 不同时报错
+ */
+pub fn from_str(json: &str) -> crate::Result<Changelog> {
+    let document: Document = serde_json::from_str(json).context(crate::ErrorKind::Json)?;
+
+    if document.schema_version != SCHEMA_VERSION {
+        return Err(crate::Error::new(
+            crate::ErrorKind::Json,
+            format!(
+                "unsupported schema_version {}, expected {}",
+                document.schema_version, SCHEMA_VERSION
+            ),
+        ));
+    }
+
+    Ok(document.changelog)
+}
+
+/**
+ * 增量更新，和 update::merge 一样只插入已有文件中没有的 release，unreleased 的总是替换
+ * 新的 release 放在最前面，新的包放在最后面
+ */
+#[must_use]
+pub fn merge(existing: Changelog, generated: Changelog) -> Changelog {
+    let mut changelog = existing;
+
+    for package in generated.packages {
+        match changelog
+            .packages
+            .iter_mut()
+            .find(|existing_package| existing_package.name == package.name)
+        {
+            Some(existing_package) => {
+                let mut release_list: Vec<_> = package
+                    .releases
+                    .into_iter()
+                    .filter(|release| {
+                        release.unreleased
+                            || !existing_package
+                                .releases
+                                .iter()
+                                .any(|existing_release| existing_release.tag == release.tag)
+                    })
+                    .collect();
+                // 之前的 unreleased 已经过期了，和 update::merge 一样替换掉
+                if !release_list.is_empty() {
+                    existing_package
+                        .releases
+                        .retain(|existing_release| !existing_release.unreleased);
+                }
+                release_list.append(&mut existing_package.releases);
+                existing_package.releases = release_list;
+            }
+            None => changelog.packages.push(package),
+        }
+    }
+
+    changelog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Author, Entry, PackageChangelog, Release, Section, UNRELEASED};
+    use serde_json::Value;
+
+    fn release(tag: &str, subject: &str) -> Release {
+        let unreleased = tag == "HEAD";
+        Release {
+            tag: tag.to_owned(),
+            version: if unreleased {
+                UNRELEASED.to_owned()
+            } else {
+                tag.rsplit('@').next().unwrap().to_owned()
+            },
+            date: "2021-10-18".to_owned(),
+            sections: vec![Section {
+                title: "Bug Fixes".to_owned(),
+                entries: vec![Entry {
+                    commit_type: "fix".to_owned(),
+                    scopes: vec!["layout".to_owned()],
+                    subject: subject.to_owned(),
+                    message: format!("fix(layout): {} (#12)", subject),
+                    breaking: false,
+                    hash: "1a2b3c4d5e6f".to_owned(),
+                    commit_url: "https://github.com/acme/app/commit/1a2b3c4d5e6f".to_owned(),
+                    pr: Some(12),
+                    pr_reference: Some("#12".to_owned()),
+                    pr_url: Some("https://github.com/acme/app/pull/12".to_owned()),
+                    authors: vec![
+                        Author {
+                            login: "alice".to_owned(),
+                            url: Some("https://github.com/alice".to_owned()),
+                        },
+                        Author {
+                            login: "Bob Smith".to_owned(),
+                            url: None,
+                        },
+                    ],
+                }],
+            }],
+            unreleased,
+        }
+    }
+
+    fn package(name: &str, releases: Vec<Release>) -> PackageChangelog {
+        PackageChangelog {
+            name: name.to_owned(),
+            scope: name.rsplit('-').next().unwrap().to_owned(),
+            releases,
+        }
+    }
+
+    fn changelog() -> Changelog {
+        Changelog {
+            packages: vec![
+                package(
+                    "@ant-design/pro-layout",
+                    vec![
+                        release("HEAD", "pending"),
+                        release("@ant-design/pro-layout@6.5.0", "released"),
+                    ],
+                ),
+                package("@ant-design/pro-form", vec![]),
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let json = to_string(&changelog()).unwrap();
+        assert!(json.starts_with("{\n  \"schema_version\": 1,"));
+        assert!(json.ends_with("}\n"));
+        assert_eq!(from_str(&json).unwrap(), changelog());
+    }
+
+    #[test]
+    fn other_schema_versions_are_rejected() {
+        let json = to_string(&changelog()).unwrap();
+
+        let error =
+            from_str(&json.replace("\"schema_version\": 1", "\"schema_version\": 2")).unwrap_err();
+        assert!(matches!(error.kind(), crate::ErrorKind::Json));
+        let source = std::error::Error::source(&error).unwrap().to_string();
+        assert_eq!(source, "unsupported schema_version 2, expected 1");
+
+        let error = from_str(&json.replace("\"schema_version\": 1,", "")).unwrap_err();
+        assert!(matches!(error.kind(), crate::ErrorKind::Json));
+    }
+
+    #[test]
+    fn merge_inserts_new_releases_and_packages() {
+        let existing = changelog();
+        let generated = Changelog {
+            packages: vec![
+                package(
+                    "@ant-design/pro-layout",
+                    vec![
+                        release("HEAD", "pending again"),
+                        release("@ant-design/pro-layout@6.6.0", "new"),
+                        release("@ant-design/pro-layout@6.5.0", "generated"),
+                    ],
+                ),
+                package(
+                    "@ant-design/pro-table",
+                    vec![release("@ant-design/pro-table@1.0.0", "first")],
+                ),
+            ],
+        };
+
+        let merged = merge(existing.clone(), generated.clone());
+        let subject_list = |package: &PackageChangelog| -> Vec<String> {
+            package
+                .releases
+                .iter()
+                .map(|release| release.sections[0].entries[0].subject.clone())
+                .collect()
+        };
+        // 已有的 release 保持不变，unreleased 被替换
+        assert_eq!(
+            subject_list(&merged.packages[0]),
+            vec!["pending again", "new", "released"]
+        );
+        assert_eq!(merged.packages[1], existing.packages[1]);
+        assert_eq!(merged.packages[2], generated.packages[1]);
+
+        assert_eq!(merge(merged.clone(), generated), merged);
+        // 没有新的 release 时保留已有的 unreleased
+        assert_eq!(merge(existing.clone(), existing.clone()), existing);
+    }
+
+    /**
+     * 按照 json schema 检查，只支持 SCHEMA 中用到的关键字：
+     * $ref、const、type、minimum、required、properties 和 items
+     * properties 中没有的字段也算错，模型加了字段时 schema 也要加上
+     */
+    fn validate(schema: &Value, root: &Value, value: &Value, path: &str) {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.strip_prefix("#/$defs/").unwrap();
+            return validate(&root["$defs"][name], root, value, path);
+        }
+
+        if let Some(expected) = schema.get("const") {
+            assert_eq!(value, expected, "{}", path);
+        }
+
+        if let Some(type_value) = schema.get("type") {
+            let type_list: Vec<&str> = match type_value {
+                Value::Array(type_list) => type_list.iter().filter_map(Value::as_str).collect(),
+                _ => vec![type_value.as_str().unwrap()],
+            };
+            let actual = match value {
+                Value::Null => "null",
+                Value::Bool(_) => "boolean",
+                Value::Number(number) if number.is_u64() || number.is_i64() => "integer",
+                Value::Number(_) => "number",
+                Value::String(_) => "string",
+                Value::Array(_) => "array",
+                Value::Object(_) => "object",
+            };
+            assert!(
+                type_list.contains(&actual),
+                "{} is {}, expected {:?}",
+                path,
+                actual,
+                type_list
+            );
+        }
+
+        if let (Some(minimum), Some(number)) = (
+            schema.get("minimum").and_then(Value::as_f64),
+            value.as_f64(),
+        ) {
+            assert!(number >= minimum, "{} is less than {}", path, minimum);
+        }
+
+        if let Some(object) = value.as_object() {
+            for key in schema["required"].as_array().into_iter().flatten() {
+                let key = key.as_str().unwrap();
+                assert!(object.contains_key(key), "{}.{} is missing", path, key);
+            }
+            let properties = schema["properties"].as_object().unwrap();
+            for (key, field) in object {
+                let field_schema = properties
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{}.{} is not in the schema", path, key));
+                validate(field_schema, root, field, &format!("{}.{}", path, key));
+            }
+        }
+
+        if let (Some(items), Some(list)) = (schema.get("items"), value.as_array()) {
+            for (index, item) in list.iter().enumerate() {
+                validate(items, root, item, &format!("{}[{}]", path, index));
+            }
+        }
+    }
+
+    #[test]
+    fn document_matches_the_schema() {
+        let schema: Value = serde_json::from_str(SCHEMA).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            SCHEMA_VERSION
+        );
+
+        let document: Value = serde_json::from_str(&to_string(&changelog()).unwrap()).unwrap();
+        validate(&schema, &schema, &document, "$");
+    }
+}
@@ -0,0 +1,440 @@
+//! The [Keep a Changelog] format, an alternative to the markdown templates.
+//!
+//! ```markdown
+//! ## [Unreleased]
+//!
+//! ## [6.5.0] - 2026-10-01
+//!
+//! ### Added
+//!
+//! - add headerContent ([#123](https://github.com/ant-design/pro-components/pull/123))
+//!
+//! [Unreleased]: https://github.com/ant-design/pro-components/compare/@ant-design/pro-layout@6.5.0...HEAD
+//! [6.5.0]: https://github.com/ant-design/pro-components/compare/@ant-design/pro-layout@6.4.0...@ant-design/pro-layout@6.5.0
+//! ```
+//!
+//! Entries are grouped by [`Category`] from their conventional commit type,
+//! entries of other types are left out. Only the types shown by the `sections`
+//! of the config are available, add a section to include e.g. `refactor`.
+//!
+//! [Keep a Changelog]: https://keepachangelog.com/en/1.1.0/
+
+use crate::model::{Entry, PackageChangelog, Release, UNRELEASED};
+use crate::update;
+use std::collections::HashMap;
+
+/// A Keep a Changelog section, in display order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Added,
+    Changed,
+    Deprecated,
+    Removed,
+    Fixed,
+    Security,
+}
+
+impl Category {
+    /// Every category, in display order.
+    pub const ALL: [Category; 6] = [
+        Category::Added,
+        Category::Changed,
+        Category::Deprecated,
+        Category::Removed,
+        Category::Fixed,
+        Category::Security,
+    ];
+
+    /**
+     * 按照 commit 的类型分类，没有对应分类的破坏性变更放在 Changed 中
+     * 其他类型例如 docs、chore 返回 None，不会出现在 changelog 中
+     */
+    #[must_use]
+    pub fn of(entry: &Entry) -> Option<Category> {
+        match entry.commit_type.as_str() {
+            "feat" => Some(Category::Added),
+            // revert 撤销的是之前的改动，不一定是删除功能
+            "perf" | "refactor" | "style" | "revert" => Some(Category::Changed),
+            "deprecate" | "deprecated" => Some(Category::Deprecated),
+            "remove" => Some(Category::Removed),
+            "fix" => Some(Category::Fixed),
+            "security" | "sec" => Some(Category::Security),
+            _ if entry.breaking => Some(Category::Changed),
+            _ => None,
+        }
+    }
+
+    /// The `###` heading, e.g. `Added`.
+    #[must_use]
+    pub fn title(self) -> &'static str {
+        match self {
+            Category::Added => "Added",
+            Category::Changed => "Changed",
+            Category::Deprecated => "Deprecated",
+            Category::Removed => "Removed",
+            Category::Fixed => "Fixed",
+            Category::Security => "Security",
+        }
+    }
+}
+
+/// The compare links at the bottom of the file.
+#[derive(Clone, Debug, Default)]
+pub struct CompareLinks {
+    /// The changes after the newest tag, e.g. `.../compare/pkg@1.2.3...HEAD`,
+    /// `None` leaves out the `[Unreleased]` link, e.g. before the first tag.
+    pub unreleased: Option<String>,
+    /// The changes of each release, by tag.
+    pub releases: HashMap<String, String>,
+}
+
+/**
+ * 渲染一个包的 Keep a Changelog 文件
+ * 文件以 `## [Unreleased]` 开头，然后是从新到旧的 release，最后是对比的链接
+ */
+#[must_use]
+pub fn render(package: &PackageChangelog, links: &CompareLinks) -> String {
+    let mut content = format!(
+        "# Changelog\n\n\
+         All notable changes to {} will be documented in this file.\n\n\
+         The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),\n\
+         and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).\n\n",
+        package.name
+    );
+    // 还没有发布的 commit 写在 Unreleased 中，没有时只有标题
+    content.push_str(&format!("## [{}]\n\n", UNRELEASED));
+    if let Some(release) = package.releases.iter().find(|release| release.unreleased) {
+        content.push_str(&release_body(release));
+    }
+
+    let mut link_list = vec![];
+    if let Some(url) = &links.unreleased {
+        link_list.push(format!("[{}]: {}", UNRELEASED, url));
+    }
+
+    for release in package
+        .releases
+        .iter()
+        .filter(|release| !release.unreleased)
+    {
+        content.push_str(&format!("## [{}] - {}\n\n", release.version, release.date));
+        content.push_str(&release_body(release));
+
+        if let Some(url) = links.releases.get(&release.tag) {
+            link_list.push(format!("[{}]: {}", release.version, url));
+        }
+    }
+
+    if !link_list.is_empty() {
+        content.push_str(&link_list.join("\n"));
+        content.push('\n');
+    }
+
+    content.trim_end().to_owned() + "\n"
+}
+
+/**
+ * 一个 release 的内容，按照 Category 的顺序分组，每组后面空一行
+ */
+fn release_body(release: &Release) -> String {
+    // 破坏性变更会同时出现在 BREAKING CHANGES 和对应类型的 section 中，只保留一次
+    let mut entry_list: Vec<&Entry> = vec![];
+    for entry in release.sections.iter().flat_map(|section| &section.entries) {
+        if !entry_list
+            .iter()
+            .any(|existing| existing.hash == entry.hash)
+        {
+            entry_list.push(entry);
+        }
+    }
+
+    let mut body = String::new();
+    for category in Category::ALL {
+        let line_list: Vec<String> = entry_list
+            .iter()
+            .filter(|entry| Category::of(entry) == Some(category))
+            .map(|entry| entry_line(entry))
+            .collect();
+        if !line_list.is_empty() {
+            body.push_str(&format!(
+                "### {}\n\n{}\n\n",
+                category.title(),
+                line_list.join("\n")
+            ));
+        }
+    }
+
+    body
+}
+
+// - add headerContent ([#123](https://github.com/ant-design/pro-components/pull/123))
+fn entry_line(entry: &Entry) -> String {
+    let mut subject = entry.subject.as_str();
+    let link = match (entry.pr, &entry.pr_url) {
+        (Some(pr), Some(pr_url)) => {
+            let reference = entry
+                .pr_reference
+                .clone()
+                .unwrap_or_else(|| format!("#{}", pr));
+            // squash 合并的标题以 (#123) 结尾，已经有链接了
+            subject = subject
+                .strip_suffix(&format!(" ({})", reference))
+                .unwrap_or(subject);
+            format!("[{}]({})", reference, pr_url)
+        }
+        _ => format!("[{}]({})", entry.short_hash(), entry.commit_url),
+    };
+    let breaking = if entry.breaking { "**BREAKING** " } else { "" };
+
+    format!("- {}{} ({})", breaking, subject, link)
+}
+
+/// Whether a line is a link reference, e.g. `[1.2.3]: https://...`.
+fn is_link_line(line: &str) -> bool {
+    line.starts_with('[') && line.contains("]: ")
+}
+
+/// The label of a `## [1.2.3] - date` heading, `1.2.3`.
+fn heading_label(heading: &str) -> &str {
+    heading
+        .strip_prefix('[')
+        .and_then(|heading| heading.split_once(']'))
+        .map_or(heading, |(label, _)| label)
+}
+
+/**
+ * 增量更新，对应 update::merge
+ * 和 update::merge 一样替换过期的 Unreleased，文件中没有的 release 插入在 Unreleased 之后
+ * 其余的 release 保持不变，对比的链接以新生成的为准，保留文件中其他的链接
+ */
+#[must_use]
+pub fn merge(existing: &str, generated: &str) -> String {
+    let strip_links = |content: &str| -> (String, Vec<String>) {
+        let mut text = String::new();
+        let mut link_list = vec![];
+        for line in content.split_inclusive('\n') {
+            if is_link_line(line) {
+                link_list.push(line.trim_end().to_owned());
+            } else {
+                text.push_str(line);
+            }
+        }
+        (text, link_list)
+    };
+
+    let (existing, existing_link_list) = strip_links(existing);
+    let (generated, generated_link_list) = strip_links(generated);
+    let (preamble, existing_release_list) = update::split_releases(&existing);
+    let (_, generated_release_list) = update::split_releases(&generated);
+
+    let existing_label_list: Vec<&str> = existing_release_list
+        .iter()
+        .map(|release| heading_label(&release.heading))
+        .collect();
+
+    let is_unreleased =
+        |release: &&update::ReleaseBlock| heading_label(&release.heading) == UNRELEASED;
+    let new_release_list: Vec<String> = generated_release_list
+        .iter()
+        .filter(|release| {
+            let label = heading_label(&release.heading);
+            label != UNRELEASED && !existing_label_list.contains(&label)
+        })
+        .map(|release| release.content.clone())
+        .collect();
+
+    // 新生成的 Unreleased 有内容，或者有新的 release 时，之前的 Unreleased 已经过期了
+    let generated_unreleased = generated_release_list.iter().find(is_unreleased);
+    let existing_unreleased = existing_release_list.iter().find(is_unreleased);
+    let unreleased = match (generated_unreleased, existing_unreleased) {
+        (Some(generated), Some(existing))
+            if new_release_list.is_empty()
+                && generated
+                    .content
+                    .lines()
+                    .skip(1)
+                    .all(|line| line.trim().is_empty()) =>
+        {
+            Some(existing)
+        }
+        (Some(generated), _) => Some(generated),
+        (None, existing) => existing,
+    };
+
+    let mut block_list: Vec<String> = vec![];
+    block_list.extend(unreleased.map(|release| release.content.clone()));
+    block_list.extend(new_release_list);
+    block_list.extend(
+        existing_release_list
+            .iter()
+            .filter(|release| !is_unreleased(release))
+            .map(|release| release.content.clone()),
+    );
+
+    let link_label = |line: &String| line.split_once("]: ").map(|(label, _)| label.to_owned());
+    let generated_label_list: Vec<_> = generated_link_list.iter().filter_map(link_label).collect();
+    let mut link_list = generated_link_list.clone();
+    link_list.extend(existing_link_list.into_iter().filter(|line| {
+        link_label(line).is_some_and(|label| !generated_label_list.contains(&label))
+    }));
+
+    let mut content = preamble;
+    for block in block_list {
+        content.push_str(block.trim_end());
+        content.push_str("\n\n");
+    }
+    content.push_str(&link_list.join("\n"));
+
+    content.trim_end().to_owned() + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "# Changelog\n\nAll notable changes.\n\n";
+
+    fn changelog(body: &str, links: &[&str]) -> String {
+        format!("{}{}\n\n{}\n", HEADER, body.trim_end(), links.join("\n"))
+    }
+
+    #[test]
+    fn inserts_new_releases_after_unreleased() {
+        let existing = changelog(
+            "## [Unreleased]\n\n### Fixed\n\n- pending\n\n## [1.0.0] - 2020-01-01\n\n### Added\n\n- edited by hand\n",
+            &["[Unreleased]: https://example.com/compare/pkg@1.0.0...HEAD", "[0.1.0]: https://example.com/old"],
+        );
+        let generated = changelog(
+            "## [Unreleased]\n\n## [1.1.0] - 2020-02-01\n\n### Fixed\n\n- pending\n\n## [1.0.0] - 2020-01-01\n\n### Added\n\n- generated\n",
+            &[
+                "[Unreleased]: https://example.com/compare/pkg@1.1.0...HEAD",
+                "[1.1.0]: https://example.com/compare/pkg@1.0.0...pkg@1.1.0",
+            ],
+        );
+
+        assert_eq!(
+            merge(&existing, &generated),
+            changelog(
+                "## [Unreleased]\n\n## [1.1.0] - 2020-02-01\n\n### Fixed\n\n- pending\n\n## [1.0.0] - 2020-01-01\n\n### Added\n\n- edited by hand\n",
+                &[
+                    "[Unreleased]: https://example.com/compare/pkg@1.1.0...HEAD",
+                    "[1.1.0]: https://example.com/compare/pkg@1.0.0...pkg@1.1.0",
+                    "[0.1.0]: https://example.com/old",
+                ],
+            )
+        );
+    }
+
+    #[test]
+    fn keeps_unreleased_when_nothing_is_new() {
+        let existing = changelog(
+            "## [Unreleased]\n\n### Fixed\n\n- edited by hand\n\n## [1.0.0] - 2020-01-01\n\n### Added\n\n- first\n",
+            &["[1.0.0]: https://example.com/1.0.0"],
+        );
+        let generated = changelog(
+            "## [Unreleased]\n\n## [1.0.0] - 2020-01-01\n\n### Added\n\n- first\n",
+            &["[1.0.0]: https://example.com/1.0.0"],
+        );
+
+        assert_eq!(merge(&existing, &generated), existing);
+    }
+
+    #[test]
+    fn merge_is_idempotent() {
+        let existing = changelog(
+            "## [Unreleased]\n\n## [1.0.0] - 2020-01-01\n\n### Added\n\n- first\n",
+            &["[1.0.0]: https://example.com/1.0.0"],
+        );
+        let generated = changelog(
+            "## [Unreleased]\n\n### Fixed\n\n- pending\n\n## [1.1.0] - 2020-02-01\n\n### Added\n\n- second\n",
+            &["[1.1.0]: https://example.com/1.1.0"],
+        );
+
+        let once = merge(&existing, &generated);
+        assert!(once.contains("- pending"));
+        assert!(once.contains("- first"));
+        assert_eq!(merge(&once, &generated), once);
+    }
+
+    fn entry(hash: &str, commit_type: &str, subject: &str, pr: Option<u64>) -> Entry {
+        Entry {
+            commit_type: commit_type.to_owned(),
+            scopes: vec![],
+            subject: subject.to_owned(),
+            message: format!("{}: {}", commit_type, subject),
+            breaking: false,
+            hash: hash.to_owned(),
+            commit_url: format!("https://example.com/commit/{}", hash),
+            pr,
+            pr_reference: pr.map(|pr| format!("!{}", pr)),
+            pr_url: pr.map(|pr| format!("https://example.com/merge_requests/{}", pr)),
+            authors: vec![],
+        }
+    }
+
+    fn package(entries: Vec<Entry>) -> PackageChangelog {
+        PackageChangelog {
+            name: "pkg".to_owned(),
+            scope: "pkg".to_owned(),
+            releases: vec![Release {
+                tag: "pkg@1.0.0".to_owned(),
+                version: "1.0.0".to_owned(),
+                date: "2020-01-01".to_owned(),
+                sections: vec![crate::model::Section {
+                    title: "Changes".to_owned(),
+                    entries,
+                }],
+                unreleased: false,
+            }],
+        }
+    }
+
+    #[test]
+    fn render_without_links() {
+        let content = render(
+            &package(vec![
+                entry("1111111", "feat", "add header (!12)", Some(12)),
+                entry("2222222", "revert", "undo footer", None),
+                entry("3333333", "docs", "readme", None),
+            ]),
+            &CompareLinks::default(),
+        );
+
+        assert!(content.contains(
+            "## [Unreleased]\n\n\
+             ## [1.0.0] - 2020-01-01\n\n\
+             ### Added\n\n\
+             - add header ([!12](https://example.com/merge_requests/12))\n\n\
+             ### Changed\n\n\
+             - undo footer ([2222222](https://example.com/commit/2222222))\n"
+        ));
+        assert!(!content.contains("]: "));
+        assert!(!content.contains("readme"));
+    }
+
+    #[test]
+    fn render_links() {
+        let links = CompareLinks {
+            unreleased: Some("https://example.com/compare/pkg@1.0.0...HEAD".to_owned()),
+            releases: HashMap::from([(
+                "pkg@1.0.0".to_owned(),
+                "https://example.com/compare/pkg@0.1.0...pkg@1.0.0".to_owned(),
+            )]),
+        };
+        let content = render(&package(vec![entry("1111111", "fix", "a", None)]), &links);
+
+        assert!(content.ends_with(
+            "[Unreleased]: https://example.com/compare/pkg@1.0.0...HEAD\n\
+             [1.0.0]: https://example.com/compare/pkg@0.1.0...pkg@1.0.0\n"
+        ));
+    }
+
+    #[test]
+    fn heading_labels() {
+        assert_eq!(heading_label("[1.2.3] - 2020-01-01"), "1.2.3");
+        assert_eq!(heading_label("[Unreleased]"), UNRELEASED);
+        assert_eq!(heading_label("1.2.3"), "1.2.3");
+        assert!(is_link_line("[1.2.3]: https://example.com"));
+        assert!(!is_link_line("[1.2.3] - 2020-01-01"));
+    }
+}
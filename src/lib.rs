@@ -0,0 +1,57 @@
+//! Changelog generation and npm publish verification for lerna style monorepos.
+//!
+//! Releases are tagged `package@version`, e.g. `@ant-design/pro-layout@6.5.0`,
+//! and commits follow [Conventional Commits] with the package scope, e.g.
+//! `feat(layout): add headerContent`.
+//!
+//! * [`git`] enumerates the tag ranges of a package, the commits after its
+//!   newest tag or between any two revisions, and collects their commits.
+//!   The repository is read with git2, which stays out of the public API.
+//! * [`changelog`] turns those commits into a [`model::Changelog`], linked
+//!   to the [`forge`], and renders it to markdown with the [`template`]s,
+//!   one file per package, or to [`html`] for docs sites or the
+//!   [`keep_a_changelog`] format, or exports it with [`json`].
+//! * [`npm`] checks that the versions in `package.json` were published.
+//!
+//! Everything is configured through [`Config`], usually read from
+//! `pro-changelog.toml` in the repository root.
+//!
+//! ```no_run
+//! use pro_changelog::{Changelogs, Config};
+//!
+//! let config = Config::load(".")?;
+//! let mut changelogs = Changelogs::new(config)?;
+//! for markdown in changelogs.get_change_log_list()? {
+//!     println!("{}\n{}", markdown.package, markdown.content);
+//! }
+//! # Ok::<(), pro_changelog::Error>(())
+//! ```
+//!
+//! [Conventional Commits]: https://www.conventionalcommits.org
+
+pub mod authors;
+pub mod changelog;
+pub mod config;
+pub mod conventional;
+mod error;
+pub mod forge;
+pub mod git;
+pub mod html;
+pub mod http;
+pub mod json;
+pub mod keep_a_changelog;
+pub mod model;
+pub mod npm;
+pub mod package;
+pub mod remote;
+#[cfg(test)]
+mod stub_server;
+pub mod template;
+pub mod update;
+
+pub use crate::changelog::{Changelogs, Markdown};
+pub use crate::config::Config;
+pub use crate::error::{Error, ErrorKind, Result};
+pub use crate::git::{latest_diff, Commit, Tag};
+pub use crate::model::Changelog;
+pub use crate::npm::Npm;
@@ -1,56 +1,489 @@
-mod changelog;
-mod error;
-mod git;
-mod npm;
-use std::io::{self, BufRead};
-use std::process::Command; // 引入命令模块
+use clap::{Args, Parser, Subcommand};
+use pro_changelog::changelog::{Changelogs, Markdown};
+use pro_changelog::config::Config;
+use pro_changelog::forge::ForgeKind;
+use pro_changelog::html::HtmlOptions;
+use pro_changelog::model::PackageChangelog;
+use pro_changelog::npm::Npm;
+use pro_changelog::{json, keep_a_changelog, update, Changelog, Result};
+use std::env;
+use std::error::Error as _;
+use std::fs::{self, create_dir_all, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
 
-use changelog::Changelogs;
-use npm::Npm;
-use std::fs::{self, create_dir, File};
-use std::io::Write;
+/// The file of the JSON export in `out_dir`.
+const JSON_FILE: &str = "changelog.json";
 
-pub use crate::error::{Error, ErrorKind, Result};
-pub use crate::git::{latest_diff, Commit, Tag};
+/// Generate changelogs and verify npm publishes for a lerna style monorepo.
+#[derive(Parser, Debug)]
+#[clap(name = "pro-changelog", version)]
+struct Cli {
+    /// Path of the git repository.
+    #[clap(short, long, global = true, default_value = ".")]
+    path: String,
 
-fn create_md_file(package: String, content: String) {
-    let dir_path = ".changelog";
+    /// Config file, defaults to `pro-changelog.toml` or `.changelogrc` in the repository.
+    #[clap(short, long, global = true)]
+    config: Option<PathBuf>,
 
-    let path = format!(".changelog/{package}.md", package = package);
+    /// Directory the files are written to, relative to the current directory.
+    /// Defaults to `out_dir` of the config, or `.changelog` in the repository.
+    #[clap(short, long, global = true)]
+    out_dir: Option<String>,
 
-    if !std::path::Path::new(&dir_path).exists() {
-        create_dir(dir_path).unwrap();
+    /// Only handle these packages, e.g. `-P layout -P form`. Defaults to all packages.
+    #[clap(short = 'P', long = "package", global = true)]
+    packages: Vec<String>,
+
+    /// Prefix stripped from npm names to get the commit scope, e.g. `@ant-design/pro-`.
+    #[clap(long, global = true)]
+    scope_prefix: Option<String>,
+
+    /// The remote used for links, defaults to `upstream` when it exists and `origin` otherwise.
+    #[clap(long, global = true)]
+    remote: Option<String>,
+
+    /// Web host of the repository, defaults to the host of the remote.
+    #[clap(long, global = true)]
+    host: Option<String>,
+
+    /// The forge hosting the repository: github, gitlab, gitea or bitbucket.
+    #[clap(long, global = true)]
+    forge: Option<ForgeKind>,
+
+    /// Overrides the API root of the forge, e.g. `https://git.example.com/api/v4`.
+    #[clap(long, global = true)]
+    api_url: Option<String>,
+
+    /// Directory with `release.hbs` and `entry.hbs` replacing the bundled templates.
+    #[clap(long, global = true)]
+    template_dir: Option<String>,
+
+    /// Do not call the GitHub API, logins come from the authors file or noreply emails, other authors are shown by name.
+    #[clap(long, global = true)]
+    offline: bool,
+
+    /// JSON file mapping commit author names or emails to GitHub logins.
+    #[clap(long, global = true)]
+    authors_file: Option<String>,
+
+    /// Timeout of a forge or npm registry request, in seconds.
+    #[clap(long, global = true, value_name = "SECONDS")]
+    http_timeout: Option<u64>,
+
+    /// Retries of a request failing with a rate limit, a 5xx or a timeout.
+    #[clap(long, global = true)]
+    http_retries: Option<u32>,
+
+    /// The npm registry checked by `check-publish`.
+    #[clap(long, global = true)]
+    npm_registry: Option<String>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate the changelog between the two latest tags of each package.
+    Latest(WriteArgs),
+    /// Generate the changelog for every tag of each package.
+    All(WriteArgs),
+    /// Generate an `Unreleased` release from the commits after the newest tag of each package.
+    Unreleased {
+        /// Read the commits from this ref instead of HEAD, e.g. a branch.
+        #[clap(long, default_value = "HEAD", value_name = "REF")]
+        head: String,
+
+        #[clap(flatten)]
+        args: WriteArgs,
+    },
+    /// Generate a release from the commits of `--to` since it diverged from `--from`, e.g. a hotfix branch.
+    ///
+    /// The release is `Unreleased` unless `--to` is a tag of the package.
+    Range {
+        /// The base revision, e.g. a tag or `master`.
+        #[clap(long, value_name = "REV")]
+        from: String,
+
+        /// The revision to read the commits from, e.g. a branch.
+        #[clap(long, default_value = "HEAD", value_name = "REV")]
+        to: String,
+
+        #[clap(flatten)]
+        args: WriteArgs,
+    },
+    /// Check that the current version of each package was published to npm.
+    CheckPublish,
+}
+
+#[derive(Args, Debug)]
+struct WriteArgs {
+    /// Only insert the releases missing from the existing files, keeping manual edits.
+    #[clap(short, long)]
+    update: bool,
+
+    /// Print a diff of the changes instead of writing, exit with 1 when there are changes.
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Output format: markdown, keep-a-changelog or html, one file per package,
+    /// or json, a single `changelog.json`.
+    #[clap(long, default_value = "markdown")]
+    format: Format,
+
+    /// With `--format html`, write fragments to embed instead of standalone pages.
+    #[clap(long)]
+    fragment: bool,
+
+    /// With `--format html`, embed an index of the entries by scope and type.
+    #[clap(long)]
+    search_index: bool,
+}
+
+/// The output format of `latest` and `all`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Markdown,
+    KeepAChangelog,
+    Html,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Format, String> {
+        match name.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Format::Markdown),
+            "keep-a-changelog" | "keepachangelog" => Ok(Format::KeepAChangelog),
+            "html" => Ok(Format::Html),
+            "json" => Ok(Format::Json),
+            _ => Err(format!(
+                "unknown format `{}`, expected markdown, keep-a-changelog, html or json",
+                name
+            )),
+        }
     }
+}
 
-    let mut buffer = File::create(path).unwrap();
+impl Cli {
+    /// Load the config file and apply the command-line overrides on top of it.
+    fn load_config(&self) -> Result<Config> {
+        let mut config = match &self.config {
+            Some(file) => Config::load_file(file)?,
+            None => Config::load(&self.path)?,
+        };
+        config.path = self.path.clone();
 
-    buffer.write_all(content.as_bytes()).unwrap();
-    buffer.flush().unwrap();
+        // 命令行的 out_dir 相对于当前目录，配置文件中的相对于仓库，见 Config::out_dir
+        if let Some(out_dir) = &self.out_dir {
+            config.out_dir = env::current_dir()?
+                .join(out_dir)
+                .to_string_lossy()
+                .into_owned();
+        }
+        if !self.packages.is_empty() {
+            config.packages = self.packages.clone();
+        }
+        if let Some(scope_prefix) = &self.scope_prefix {
+            config.scope_prefix = Some(scope_prefix.clone());
+        }
+        if let Some(remote) = &self.remote {
+            config.remote = Some(remote.clone());
+        }
+        if let Some(host) = &self.host {
+            config.host = Some(host.clone());
+        }
+        if let Some(forge) = self.forge {
+            config.forge = Some(forge);
+        }
+        if let Some(api_url) = &self.api_url {
+            config.api_url = Some(api_url.clone());
+        }
+        if let Some(template_dir) = &self.template_dir {
+            config.template_dir = Some(template_dir.clone());
+        }
+        if self.offline {
+            config.offline = true;
+        }
+        if let Some(authors_file) = &self.authors_file {
+            config.authors_file = Some(authors_file.clone());
+        }
+        if let Some(http_timeout) = self.http_timeout {
+            config.http_timeout = http_timeout;
+        }
+        if let Some(http_retries) = self.http_retries {
+            config.http_retries = http_retries;
+        }
+        if let Some(npm_registry) = &self.npm_registry {
+            config.npm_registry = npm_registry.clone();
+        }
+
+        Ok(config)
+    }
+}
+
+/// A generated changelog file.
+struct OutputFile {
+    /// Printed while writing, the package scope or the file name.
+    name: String,
+    path: PathBuf,
+    content: String,
+}
+
+impl OutputFile {
+    /// The markdown file of a package, `{scope}.md` in `out_dir`.
+    fn markdown(out_dir: &Path, scope: &str, content: String) -> OutputFile {
+        OutputFile {
+            name: scope.to_owned(),
+            path: out_dir.join(format!("{scope}.md", scope = scope)),
+            content,
+        }
+    }
+
+    /// The markdown files rendered with the templates.
+    fn markdown_list(out_dir: &Path, md_file_content_list: Vec<Markdown>) -> Vec<OutputFile> {
+        md_file_content_list
+            .into_iter()
+            .map(|md_file_content| {
+                OutputFile::markdown(out_dir, &md_file_content.package, md_file_content.content)
+            })
+            .collect()
+    }
+
+    /// The HTML page of a package, `{scope}.html` in `out_dir`.
+    fn html(out_dir: &Path, package: &PackageChangelog, content: String) -> OutputFile {
+        OutputFile {
+            name: package.scope.clone(),
+            path: out_dir.join(format!("{scope}.html", scope = package.scope)),
+            content,
+        }
+    }
+
+    /// The JSON export of every package, `changelog.json` in `out_dir`.
+    fn json(out_dir: &Path, changelog: &Changelog) -> Result<OutputFile> {
+        Ok(OutputFile {
+            name: JSON_FILE.to_owned(),
+            path: out_dir.join(JSON_FILE),
+            content: json::to_string(changelog)?,
+        })
+    }
+}
+
+/**
+ * 读取文件现在的内容，update 时用 format 对应的方式合并出新的内容
+ */
+fn gen_file(file: &OutputFile, format: Format, update: bool) -> Result<(String, String)> {
+    let old_content = if file.path.exists() {
+        fs::read_to_string(&file.path)?
+    } else {
+        String::new()
+    };
+
+    // 增量更新，只插入文件中没有的 release，html 总是重新生成
+    let new_content = match format {
+        _ if !update || old_content.is_empty() => file.content.clone(),
+        Format::Markdown => update::merge(&old_content, &file.content),
+        Format::KeepAChangelog => keep_a_changelog::merge(&old_content, &file.content),
+        Format::Html => file.content.clone(),
+        Format::Json => json::to_string(&json::merge(
+            json::from_str(&old_content)?,
+            json::from_str(&file.content)?,
+        ))?,
+    };
+
+    Ok((old_content, new_content))
+}
+
+fn create_file(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+
+    let mut buffer = File::create(path)?;
+
+    buffer.write_all(content.as_bytes())?;
+    buffer.flush()
+}
+
+/**
+ * diff 中显示的路径，相对于仓库，out_dir 不在仓库中时相对于 out_dir
+ */
+fn diff_path(path: &Path, repo: &Path, out_dir: &Path) -> String {
+    path.strip_prefix(repo)
+        .or_else(|_| path.strip_prefix(out_dir))
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/**
+ * 写入所有的 changelog，dry run 时只打印 diff 不写入
+ * dry run 时有改动返回 false，用于在 CI 中检查 changelog 是否过期
+ */
+fn write_file_list(
+    file_list: Vec<OutputFile>,
+    repo: &Path,
+    out_dir: &Path,
+    args: &WriteArgs,
+) -> Result<bool> {
+    let mut changed = false;
+
+    for file in file_list {
+        let (old_content, new_content) = gen_file(&file, args.format, args.update)?;
+
+        if args.dry_run {
+            if old_content != new_content {
+                changed = true;
+                print!(
+                    "{}",
+                    update::unified_diff(
+                        &diff_path(&file.path, repo, out_dir),
+                        &old_content,
+                        &new_content
+                    )
+                );
+            }
+            continue;
+        }
+
+        println!("-> 正在生成 {} 的 changelog", file.name);
+        create_file(&file.path, &new_content)?;
+    }
+
+    if !args.dry_run {
+        println!("🆗 生成完成。");
+    } else if changed {
+        println!("😟 changelog 不是最新的。");
+    } else {
+        println!("🆗 changelog 已经是最新的。");
+    }
+
+    Ok(!changed)
+}
+
+/**
+ * 按照 format 把 changelog 转成要写入的文件
+ * markdown、keep-a-changelog 和 html 每个包一个文件，json 所有的包写在一个文件里
+ */
+fn gen_file_list(
+    changelogs: &Changelogs,
+    changelog: &Changelog,
+    out_dir: &Path,
+    args: &WriteArgs,
+) -> Result<Vec<OutputFile>> {
+    match args.format {
+        Format::Markdown => {
+            let md_file_content_list = changelog
+                .packages
+                .iter()
+                .map(|package| changelogs.render_markdown(package))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(OutputFile::markdown_list(out_dir, md_file_content_list))
+        }
+        Format::KeepAChangelog => changelog
+            .packages
+            .iter()
+            .map(|package| {
+                let content = changelogs.render_keep_a_changelog(package)?;
+                Ok(OutputFile::markdown(out_dir, &package.scope, content))
+            })
+            .collect(),
+        Format::Html => {
+            let options = HtmlOptions {
+                standalone: !args.fragment,
+                search_index: args.search_index,
+            };
+            changelog
+                .packages
+                .iter()
+                .map(|package| {
+                    let content = changelogs.render_html(package, &options)?;
+                    Ok(OutputFile::html(out_dir, package, content))
+                })
+                .collect()
+        }
+        Format::Json => Ok(vec![OutputFile::json(out_dir, changelog)?]),
+    }
+}
+
+/**
+ * 保存作者缓存，然后按照 format 写入 changelog
+ * skip_empty 时跳过没有 release 的包
+ */
+fn write_changelog(
+    changelogs: &Changelogs,
+    mut changelog: Changelog,
+    repo: &Path,
+    out_dir: &Path,
+    args: &WriteArgs,
+    skip_empty: bool,
+) -> Result<bool> {
+    if skip_empty {
+        changelog
+            .packages
+            .retain(|package| !package.releases.is_empty());
+    }
+    if !args.dry_run {
+        changelogs.save_author_cache()?;
+    }
+    let file_list = gen_file_list(changelogs, &changelog, out_dir, args)?;
+    write_file_list(file_list, repo, out_dir, args)
+}
+
+fn run(cli: Cli) -> Result<bool> {
+    let config = cli.load_config()?;
+    let repo = PathBuf::from(&config.path);
+    let out_dir = config.out_dir();
+
+    match cli.command {
+        Command::Latest(args) => {
+            // 只写入 latest，最新的 tag 没有内容的包会被跳过
+            let mut changelogs = Changelogs::new(config)?;
+            let changelog = changelogs.latest_changelog()?;
+            write_changelog(&changelogs, changelog, &repo, &out_dir, &args, true)
+        }
+        Command::All(args) => {
+            // 全部的 tag 写入
+            let mut changelogs = Changelogs::new(config)?;
+            let changelog = changelogs.full_changelog()?;
+            write_changelog(&changelogs, changelog, &repo, &out_dir, &args, false)
+        }
+        Command::Unreleased { head, args } => {
+            // 最新的 tag 之后还没有发布的 commit
+            let mut changelogs = Changelogs::new(config)?;
+            let changelog = changelogs.unreleased_changelog(&head)?;
+            write_changelog(&changelogs, changelog, &repo, &out_dir, &args, true)
+        }
+        Command::Range { from, to, args } => {
+            // 任意两个 revspec 之间的 commit
+            let mut changelogs = Changelogs::new(config)?;
+            let changelog = changelogs.range_changelog(&from, &to)?;
+            write_changelog(&changelogs, changelog, &repo, &out_dir, &args, true)
+        }
+        Command::CheckPublish => Npm::new(config)?.check(),
+    }
 }
 
 fn main() {
-    // fs::remove_file(".changelog").expect("删除文件失败");
-    // // 只写入 latest
-    // let md_file_content_list =
-    //     Changelogs::new("C:/github/pro-components".to_string()).get_change_log_list();
-
-    // for md_file_content in md_file_content_list {
-    //     println!("-> 正在生成 {} 的 changelog", md_file_content.package);
-    //     create_md_file(md_file_content.package, md_file_content.content);
-    // }
-    // println!("{:?}", "🆗 生成完成。");
-
-    // // 全部的 tag 写入
-    // let md_file_content_list =
-    //     Changelogs::new("C:/github/pro-components".to_string()).get_all_change_log_list();
-
-    // for md_file_content in md_file_content_list {
-    // println!("-> 正在生成 {} 的 changelog", md_file_content.package);
-    //     create_md_file(md_file_content.package, md_file_content.content);
-    // }
-    // println!("{:?}", "🆗 生成完成。");
-
-    let s = Npm::new("C:/github/pro-components".to_string()).check();
-
-    println!("{:?}", s);
+    let cli = Cli::parse();
+
+    match run(cli) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("😟 {}", err);
+            // 逐层打印原因，例如 io 错误和 http 错误
+            let mut source = err.source();
+            while let Some(cause) = source {
+                eprintln!("   原因: {}", cause);
+                source = cause.source();
+            }
+            process::exit(1);
+        }
+    }
 }
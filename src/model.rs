@@ -0,0 +1,102 @@
+//! The structured changelog every renderer and exporter is built from.
+//!
+//! ```text
+//! Changelog
+//! └── PackageChangelog   one per package
+//!     └── Release        one per tag, newest first
+//!         └── Section    in config order, e.g. `Features`
+//!             └── Entry  one per commit
+//! ```
+//!
+//! All types are serializable with serde, links to the forge are resolved
+//! when the model is built so renderers do not need the repository.
+
+use serde::{Deserialize, Serialize};
+
+/// The version of an unreleased [`Release`].
+pub const UNRELEASED: &str = "Unreleased";
+
+/// The changelogs of the selected packages.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Changelog {
+    pub packages: Vec<PackageChangelog>,
+}
+
+/// The releases of one package.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageChangelog {
+    /// The npm name, e.g. `@ant-design/pro-layout`.
+    pub name: String,
+    /// The commit scope, also the name of the markdown file, e.g. `layout`.
+    pub scope: String,
+    /// The releases with at least one entry, newest first.
+    pub releases: Vec<Release>,
+}
+
+/// One tagged release.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    /// The git tag, e.g. `@ant-design/pro-layout@6.5.0`.
+    pub tag: String,
+    /// The version part of the tag, e.g. `6.5.0`.
+    pub version: String,
+    /// The date of the tagged commit, `YYYY-MM-DD`.
+    pub date: String,
+    /// The non empty sections, in config order.
+    pub sections: Vec<Section>,
+    /// The commits after the newest tag, `tag` is then the ref they were read
+    /// from, e.g. `HEAD`, or `from...to` for a range, and `version` is [`UNRELEASED`].
+    #[serde(default)]
+    pub unreleased: bool,
+}
+
+/// A group of entries, e.g. `Features`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Section {
+    pub title: String,
+    pub entries: Vec<Entry>,
+}
+
+/// One commit of a release.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The conventional commit type, e.g. `feat`.
+    #[serde(rename = "type")]
+    pub commit_type: String,
+    /// The scopes, `fix(form,layout)` has two.
+    pub scopes: Vec<String>,
+    /// The description after the colon.
+    pub subject: String,
+    /// The first line of the commit message.
+    pub message: String,
+    pub breaking: bool,
+    /// The full commit hash.
+    pub hash: String,
+    pub commit_url: String,
+    /// The pull request number, when the commit was merged from one.
+    pub pr: Option<u64>,
+    /// How the forge refers to the pull request, `#123`, or `!123` on GitLab.
+    #[serde(default)]
+    pub pr_reference: Option<String>,
+    pub pr_url: Option<String>,
+    /// The pull request author, or the commit author, followed by the co-authors.
+    pub authors: Vec<Author>,
+}
+
+impl Entry {
+    /// The first 7 characters of the hash.
+    #[must_use]
+    pub fn short_hash(&self) -> &str {
+        self.hash.get(..7).unwrap_or(&self.hash)
+    }
+}
+
+/// A contributor of an entry.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Author {
+    /// The forge login, or the plain name when the login isn't known.
+    pub login: String,
+    /// The profile url, `None` when the login isn't known.
+    #[serde(default)]
+    pub url: Option<String>,
+}
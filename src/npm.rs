@@ -1,58 +1,72 @@
-﻿use git2::{string_array::StringArray, Repository};
-use reqwest::Client;
+﻿//! Verification that the packages were published to the npm registry,
+//! with a rollback of the `latest` dist-tag when they were not.
+
+use reqwest::StatusCode;
 use semver::Version;
 use serde::Deserialize;
-use serde_json;
-use std::{collections::HashMap, env, fs, io, process::Command, rc::Rc};
+use std::{collections::HashMap, env, io, process::Command};
 
+use crate::config::Config;
+use crate::error::ResultExt;
 use crate::git::get_version;
+use crate::http::{HttpClient, HttpError};
+use crate::package::{self, Package};
 
+/// The npm executable.
 #[cfg(windows)]
-pub const NPM: &'static str = "npm.cmd";
+pub const NPM: &str = "npm.cmd";
 
+/// The npm executable.
 #[cfg(not(windows))]
-pub const NPM: &'static str = "npm";
+pub const NPM: &str = "npm";
 
+/// The version document returned by the npm registry, only the fields read here.
 #[derive(Deserialize)]
 pub struct NpmPackageInfo {
-    name: String,
-    version: String,
+    pub name: String,
+    pub version: String,
 }
 
+/// Checks the packages of a repository against the npm registry.
 pub struct Npm {
-    client: Client,
+    client: HttpClient,
+    /// The registry url without the trailing slash.
+    registry: String,
     path: String,
-    package_list: Vec<NpmPackageInfo>,
+    package_list: Vec<Package>,
 }
 
 impl Npm {
-    pub fn check(&self) {
-        let map = self.check_package_list_publish_success();
+    /**
+     * 检查所有包是否发布成功，失败时回滚 latest 标签
+     * 全部发布成功才返回 true
+     */
+    pub fn check(&self) -> crate::Result<bool> {
+        let map = self.check_package_list_publish_success()?;
 
-        let all_published = map.iter().any(|(package, published)| -> bool {
-            if published.to_owned().to_owned() {
-                return true;
-            }
+        let failed_list = map
+            .iter()
+            .filter(|(_, published)| !**published)
+            .map(|(package, _)| package)
+            .collect::<Vec<_>>();
+
+        for package in &failed_list {
             println!("😟 {} 发布失败！", package);
-            false
-        });
+        }
+
+        let all_published = failed_list.is_empty();
 
         if all_published {
             println!("🆗 全部发布成功");
         } else {
             println!("😟 正在回滚！");
-            let pre_package_version_list = self.get_pre_package_version();
-            let path = env::var("path").unwrap();
-            let npm_path = path
-                .split(";")
-                .find(|path| {
-                    if path.contains("nodejs") {
-                        return true;
-                    }
-
-                    false
-                })
-                .unwrap();
+            let pre_package_version_list = self.get_pre_package_version()?;
+            // windows 上在 nodejs 的目录中执行 npm，找不到时使用当前目录
+            let npm_path = env::var("path").ok().and_then(|path| {
+                path.split(';')
+                    .find(|path| path.contains("nodejs"))
+                    .map(|path| path.to_owned())
+            });
 
             for pre_package_version in &pre_package_version_list {
                 println!(
@@ -63,19 +77,20 @@ impl Npm {
 
                 let mut input = String::new();
 
-                io::stdin().read_line(&mut input).expect("读取失败");
+                io::stdin().read_line(&mut input)?;
 
-                let output = Command::new(NPM)
+                let mut command = Command::new(NPM);
+                if let Some(npm_path) = &npm_path {
+                    command.current_dir(npm_path);
+                }
+                let output = command
                     .env("NPM_CONFIG_OTP", input.trim())
-                    .current_dir(npm_path.clone())
                     .arg("dist-tag")
                     .arg("add")
                     .arg(pre_package_version)
                     .arg("latest")
-                    .spawn()
-                    .expect("执行异常，提示")
-                    .wait_with_output()
-                    .unwrap();
+                    .spawn()?
+                    .wait_with_output()?;
 
                 let output_string = String::from_utf8_lossy(&output.stderr);
 
@@ -95,60 +110,80 @@ impl Npm {
                 }
             }
         }
+
+        Ok(all_published)
     }
 
-    pub fn check_package_list_publish_success(&self) -> HashMap<String, bool> {
+    /**
+     * 检查每个包的版本是否已经发布，key 是包的 npm name
+     * package.json 中没有 version 的包不会发布，跳过
+     */
+    pub fn check_package_list_publish_success(&self) -> crate::Result<HashMap<String, bool>> {
         let mut map: HashMap<String, bool> = HashMap::new();
         for package_info in &self.package_list {
-            let is_publish = self
-                .check_publish_success(package_info.name.as_str(), package_info.version.as_str());
+            let version = match &package_info.version {
+                Some(version) => version,
+                None => continue,
+            };
+            let is_publish = self.check_publish_success(package_info.name.as_str(), version)?;
             map.insert(package_info.name.clone(), is_publish);
         }
-        map
+        Ok(map)
     }
 
     /**
      * 判断这个版本是不是发布成功了
+     * registry 返回 404 说明没有发布，限流和超时等错误会重试，最后返回错误而不是 false
      */
-    pub fn check_publish_success(&self, name: &str, version: &str) -> bool {
+    pub fn check_publish_success(&self, name: &str, version: &str) -> crate::Result<bool> {
         let endpoint = format!(
-            "https://registry.npmjs.org/{name}/{version}",
+            "{registry}/{name}/{version}",
+            registry = self.registry,
             name = name,
             version = version
         );
 
-        self.client
-            .get(&endpoint)
-            .send()
-            .unwrap()
-            .json::<NpmPackageInfo>()
-            .is_ok()
+        match self
+            .client
+            .get_json::<NpmPackageInfo>(self.client.get(&endpoint))
+        {
+            Ok(_) => Ok(true),
+            Err(error) if error.status() == Some(StatusCode::NOT_FOUND.as_u16()) => Ok(false),
+            Err(error) => Err(registry_error(error, format!("{}@{}", name, version))),
+        }
     }
     /**
      * 获取  latest 的最后一个版本
      */
-    pub fn get_package_latest_version(&self, name: &str) -> String {
-        let endpoint = format!("https://registry.npmjs.org/{name}/latest", name = name,);
-
-        self.client
-            .get(&endpoint)
-            .send()
-            .unwrap()
-            .json::<NpmPackageInfo>()
-            .unwrap()
-            .version
+    pub fn get_package_latest_version(&self, name: &str) -> crate::Result<String> {
+        let endpoint = format!(
+            "{registry}/{name}/latest",
+            registry = self.registry,
+            name = name
+        );
+
+        let package_info = self
+            .client
+            .get_json::<NpmPackageInfo>(self.client.get(&endpoint))
+            .map_err(|error| registry_error(error, name.to_owned()))?;
+
+        Ok(package_info.version)
     }
 
-    pub fn get_pre_package_version(&self) -> Vec<String> {
-        let repo = Repository::open(&self.path).unwrap();
-        let mut tag_list = repo
-            .tag_names(None)
-            .unwrap()
+    /**
+     * 获取每个包的上一个版本的 tag，没有上一个版本的包不回滚
+     */
+    pub fn get_pre_package_version(&self) -> crate::Result<Vec<String>> {
+        let repo = crate::git::open(&self.path)?;
+        let tag_names = repo.tag_names(None).context(crate::ErrorKind::Git)?;
+        let mut tag_list = tag_names
             .iter()
+            .flatten()
             .filter_map(|tag| {
-                Version::parse(&get_version(tag.unwrap()).to_owned().version)
+                let version = get_version(tag).ok()?;
+                Version::parse(&version.version)
                     .ok()
-                    .map(|version| (tag.unwrap().to_string(), version))
+                    .map(|version| (tag.to_string(), version))
             })
             .collect::<Vec<_>>();
 
@@ -162,40 +197,30 @@ impl Npm {
         let pre_package_version = self
             .package_list
             .iter()
-            .map(|package| -> String {
+            .filter_map(|package| {
                 let package_name = package.name.as_str();
                 let tag = sort_tags
-                    .clone()
-                    .into_iter()
-                    .filter(|tag| tag.contains(package_name))
-                    .collect::<Vec<_>>()
-                    .get(1)
-                    .unwrap()
-                    .clone();
+                    .iter()
+                    .filter(|tag| {
+                        get_version(tag).is_ok_and(|version| version.package == package_name)
+                    })
+                    .nth(1)
+                    .cloned();
+                if tag.is_none() {
+                    println!("😟 {} 没有可以回滚的版本", package_name);
+                }
                 tag
             })
             .collect();
 
-        pre_package_version
+        Ok(pre_package_version)
     }
-    pub fn new(path: String) -> Npm {
-        let client = Client::new();
-        let packages_path = format!("{path}/packages/", path = path);
-        let package_list: Vec<NpmPackageInfo> = fs::read_dir(&packages_path)
-            .unwrap()
-            .map(|entry| {
-                let entry = entry.unwrap();
-                let path = entry.path();
-                let path = path.to_str().unwrap();
-                let data = fs::read_to_string(format!("{path}/package.json", path = path))
-                    .expect("未找到 package.json");
-
-                let package_info: NpmPackageInfo =
-                    serde_json::from_str(&data).expect("格式化  package.json失败 ");
-
-                package_info
-            })
-            .collect();
+    /**
+     * 初始化，只检查 config.packages 中配置的包，没有配置时检查全部
+     */
+    pub fn new(config: Config) -> crate::Result<Npm> {
+        let client = HttpClient::new(config.http_options())?;
+        let package_list = package::discover(&config)?;
 
         println!("🔍 发现了{} 个 包 ->", &package_list.len());
         println!("-------------------");
@@ -205,10 +230,23 @@ impl Npm {
 
         println!("🔚🔚🔚🔚🔚🔚🔚🔚🔚🔚🔚");
 
-        Npm {
-            path,
+        Ok(Npm {
+            registry: config.npm_registry.trim_end_matches('/').to_owned(),
+            path: config.path,
             client,
             package_list,
-        }
+        })
     }
 }
+
+/// `Network` when the registry was not reached, `NpmRegistry` for the package otherwise.
+fn registry_error(error: HttpError, package: String) -> crate::Error {
+    let kind = if error.is_network() {
+        crate::ErrorKind::Network {
+            url: error.url().to_owned(),
+        }
+    } else {
+        crate::ErrorKind::NpmRegistry { package }
+    };
+    crate::Error::new(kind, error)
+}
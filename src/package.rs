@@ -0,0 +1,204 @@
+//! Discovery of the packages of the monorepo.
+
+use crate::config::Config;
+use crate::error::ResultExt;
+use serde::Deserialize;
+use std::{fs, path::PathBuf};
+
+/// A package of the monorepo, read from `packages/*/package.json`.
+#[derive(Clone, Debug)]
+pub struct Package {
+    /// The npm name, e.g. `@ant-design/pro-layout`.
+    pub name: String,
+    /// The version declared in `package.json`, `None` when it has none.
+    pub version: Option<String>,
+    /// The directory of the package.
+    pub dir: PathBuf,
+    /// The short name used as commit scope, e.g. `layout`.
+    pub scope: String,
+}
+
+#[derive(Deserialize)]
+struct PackageJson {
+    name: String,
+    version: Option<String>,
+    /// Private packages are never published, they get no changelog.
+    #[serde(default)]
+    private: bool,
+}
+
+impl Package {
+    /// Whether the package is selected by a filter of scopes or npm names.
+    ///
+    /// An empty filter selects every package.
+    #[must_use]
+    pub fn matches(&self, filter: &[String]) -> bool {
+        filter.is_empty()
+            || filter
+                .iter()
+                .any(|name| *name == self.scope || *name == self.name)
+    }
+}
+
+/**
+ * 读取 packages 下所有的 package.json，没有 package.json 的目录和 private 的包会被跳过
+ * 只返回 config.packages 中配置的包，没有配置时返回全部
+ */
+pub fn discover(config: &Config) -> crate::Result<Vec<Package>> {
+    let packages_path = PathBuf::from(&config.path).join("packages");
+
+    let mut package_list = vec![];
+
+    for entry in fs::read_dir(&packages_path)? {
+        let dir = entry?.path();
+        let package_json_path = dir.join("package.json");
+
+        if !package_json_path.is_file() {
+            continue;
+        }
+
+        let data = fs::read_to_string(&package_json_path)?;
+        let package_json: PackageJson =
+            serde_json::from_str(&data).with_context(|_| crate::ErrorKind::Config {
+                path: package_json_path.display().to_string(),
+            })?;
+
+        if package_json.private {
+            continue;
+        }
+
+        // @ant-design/pro-layout -> layout，没有配置前缀时使用目录名
+        let scope = match config
+            .scope_prefix
+            .as_deref()
+            .and_then(|prefix| package_json.name.strip_prefix(prefix))
+        {
+            Some(scope) => scope.to_owned(),
+            None => dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+
+        let package = Package {
+            name: package_json.name,
+            version: package_json.version,
+            dir,
+            scope,
+        };
+
+        if package.matches(&config.packages) {
+            package_list.push(package);
+        }
+    }
+
+    package_list.sort_by(|a, b| a.scope.cmp(&b.scope));
+
+    Ok(package_list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * 在临时目录中生成 packages 下每个包的 package.json，内容为 None 时只创建目录
+     */
+    fn monorepo(name: &str, package_list: &[(&str, Option<&str>)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "pro-changelog-packages-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+
+        for (package_dir, package_json) in package_list {
+            let package_dir = dir.join("packages").join(package_dir);
+            fs::create_dir_all(&package_dir).unwrap();
+            if let Some(package_json) = package_json {
+                fs::write(package_dir.join("package.json"), package_json).unwrap();
+            }
+        }
+
+        dir
+    }
+
+    fn scopes_and_names(package_list: &[Package]) -> Vec<(&str, &str)> {
+        package_list
+            .iter()
+            .map(|package| (package.scope.as_str(), package.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn discover_skips_private_packages() {
+        let dir = monorepo(
+            "private",
+            &[
+                (
+                    "layout",
+                    Some(r#"{"name": "@ant-design/pro-layout", "version": "6.5.0"}"#),
+                ),
+                (
+                    "form-dir",
+                    Some(r#"{"name": "@ant-design/pro-form", "version": "1.0.0"}"#),
+                ),
+                (
+                    "docs",
+                    Some(r#"{"name": "@ant-design/pro-docs", "private": true}"#),
+                ),
+                ("utils", Some(r#"{"name": "utils-lib"}"#)),
+                ("empty", None),
+            ],
+        );
+        let mut config = Config {
+            path: dir.display().to_string(),
+            scope_prefix: Some("@ant-design/pro-".to_owned()),
+            ..Config::default()
+        };
+
+        let package_list = discover(&config).unwrap();
+        // 有前缀的用去掉前缀的 npm 名，没有前缀的用目录名
+        assert_eq!(
+            scopes_and_names(&package_list),
+            vec![
+                ("form", "@ant-design/pro-form"),
+                ("layout", "@ant-design/pro-layout"),
+                ("utils", "utils-lib"),
+            ]
+        );
+        assert_eq!(package_list[1].version.as_deref(), Some("6.5.0"));
+        assert_eq!(package_list[1].dir, dir.join("packages").join("layout"));
+        assert_eq!(package_list[2].version, None);
+
+        config.packages = vec!["layout".to_owned(), "utils-lib".to_owned()];
+        assert_eq!(
+            scopes_and_names(&discover(&config).unwrap()),
+            vec![("layout", "@ant-design/pro-layout"), ("utils", "utils-lib")]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn scope_defaults_to_the_directory_name() {
+        let dir = monorepo(
+            "scope",
+            &[(
+                "pro-layout",
+                Some(r#"{"name": "@ant-design/pro-layout", "version": "6.5.0"}"#),
+            )],
+        );
+        let config = Config {
+            path: dir.display().to_string(),
+            ..Config::default()
+        };
+
+        assert_eq!(
+            scopes_and_names(&discover(&config).unwrap()),
+            vec![("pro-layout", "@ant-design/pro-layout")]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
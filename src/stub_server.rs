@@ -0,0 +1,88 @@
+//! A minimal HTTP server for tests, answering each connection with the next
+//! canned response.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// A canned response, see [`response`].
+pub struct StubResponse(String);
+
+/**
+ * 生成一个响应，headers 例如 [("Retry-After", "0")]
+ */
+pub fn response(status: u16, headers: &[(&str, &str)], body: &str) -> StubResponse {
+    let mut raw = format!("HTTP/1.1 {} Stub\r\n", status);
+    for (name, value) in headers {
+        raw.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    raw.push_str(&format!(
+        "Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    ));
+    StubResponse(raw)
+}
+
+/// A running stub server, it stops after the last response.
+pub struct StubServer {
+    /// The root url, e.g. `http://127.0.0.1:12345`.
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl StubServer {
+    /**
+     * 在随机的端口上启动，每个连接按顺序返回一个响应
+     */
+    pub fn start(response_list: Vec<StubResponse>) -> StubServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let recorded = Arc::clone(&requests);
+        thread::spawn(move || {
+            for StubResponse(raw) in response_list {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                // 读完 header 和 body，否则客户端可能收到 connection reset
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                recorded.lock().unwrap().push(format!(
+                    "{} {}",
+                    request_line.trim_end(),
+                    String::from_utf8_lossy(&body)
+                ));
+                stream.write_all(raw.as_bytes()).unwrap();
+            }
+        });
+
+        StubServer { url, requests }
+    }
+
+    /// The requests received so far, `GET /path HTTP/1.1 body`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
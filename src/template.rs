@@ -0,0 +1,323 @@
+//! Handlebars rendering of a release.
+//!
+//! Two templates are used, both can be replaced by a file of the same name in
+//! the `template_dir` of the config:
+//!
+//! * `release.hbs` renders a [`ReleaseContext`], the `## tag` heading, the date
+//!   line and the sections.
+//! * `entry.hbs` renders one [`EntryContext`], it is available as the
+//!   `{{> entry}}` partial in `release.hbs`.
+//!
+//! The [`html`](crate::html) renderer registers its own templates the same way.
+//!
+//! The bundled templates live in the `templates` directory of this crate.
+//! The contexts are built from the [`model`](crate::model).
+
+use crate::error::ResultExt;
+use crate::model::{Author, Entry, PackageChangelog, Release, Section};
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::{fs, path::Path};
+
+const RELEASE_TEMPLATE: &str = include_str!("../templates/release.hbs");
+const ENTRY_TEMPLATE: &str = include_str!("../templates/entry.hbs");
+
+/// The context of `release.hbs`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReleaseContext {
+    /// The npm name of the package, e.g. `@ant-design/pro-layout`.
+    pub package: String,
+    /// The git tag, e.g. `@ant-design/pro-layout@6.5.0`.
+    pub tag: String,
+    /// The version part of the tag, e.g. `6.5.0`.
+    pub version: String,
+    /// The date of the tagged commit, `YYYY-MM-DD`.
+    pub date: String,
+    /// The non empty sections, in config order.
+    pub sections: Vec<SectionContext>,
+    /// The commits after the newest tag, `tag` is then the ref they were read from.
+    pub unreleased: bool,
+}
+
+/// A section of a release, e.g. `Features`.
+#[derive(Clone, Debug, Serialize)]
+pub struct SectionContext {
+    pub title: String,
+    pub entries: Vec<EntryContext>,
+}
+
+/// The context of `entry.hbs`, one commit of a release.
+#[derive(Clone, Debug, Serialize)]
+pub struct EntryContext {
+    /// The first line of the commit message.
+    pub message: String,
+    /// The conventional commit type, e.g. `feat`.
+    pub commit_type: String,
+    pub scopes: Vec<String>,
+    /// The description after the colon.
+    pub subject: String,
+    pub breaking: bool,
+    pub hash: String,
+    /// The first 7 characters of the hash.
+    pub short_hash: String,
+    pub commit_url: String,
+    /// The pull request, e.g. `#123` or `!123` on GitLab, when the commit was merged from one.
+    pub pr: Option<String>,
+    pub pr_url: Option<String>,
+    /// The pull request author, or the commit author, followed by the co-authors.
+    pub authors: Vec<AuthorContext>,
+}
+
+/// A contributor linked from an entry.
+#[derive(Clone, Debug, Serialize)]
+pub struct AuthorContext {
+    /// The forge login, or the plain name when the login isn't known.
+    pub login: String,
+    /// The profile url, only set when the login is known.
+    pub url: Option<String>,
+}
+
+impl ReleaseContext {
+    /// The context of a release of the package `package`, the npm name.
+    #[must_use]
+    pub fn new(package: &str, release: &Release) -> ReleaseContext {
+        ReleaseContext {
+            package: package.to_owned(),
+            tag: release.tag.clone(),
+            version: release.version.clone(),
+            date: release.date.clone(),
+            sections: release.sections.iter().map(SectionContext::from).collect(),
+            unreleased: release.unreleased,
+        }
+    }
+}
+
+impl From<&Section> for SectionContext {
+    fn from(section: &Section) -> SectionContext {
+        SectionContext {
+            title: section.title.clone(),
+            entries: section.entries.iter().map(EntryContext::from).collect(),
+        }
+    }
+}
+
+impl From<&Entry> for EntryContext {
+    fn from(entry: &Entry) -> EntryContext {
+        EntryContext {
+            message: entry.message.clone(),
+            commit_type: entry.commit_type.clone(),
+            scopes: entry.scopes.clone(),
+            subject: entry.subject.clone(),
+            breaking: entry.breaking,
+            hash: entry.hash.clone(),
+            short_hash: entry.short_hash().to_owned(),
+            commit_url: entry.commit_url.clone(),
+            pr: entry
+                .pr_reference
+                .clone()
+                .or_else(|| entry.pr.map(|pr| format!("#{}", pr))),
+            pr_url: entry.pr_url.clone(),
+            authors: entry.authors.iter().map(AuthorContext::from).collect(),
+        }
+    }
+}
+
+impl From<&Author> for AuthorContext {
+    fn from(author: &Author) -> AuthorContext {
+        AuthorContext {
+            login: author.login.clone(),
+            url: author.url.clone(),
+        }
+    }
+}
+
+/**
+ * 注册一个模板，template_dir 中存在同名的 .hbs 文件时使用这个文件
+ * entry 结尾的模板是行内的 partial，去掉文件末尾的换行
+ */
+pub(crate) fn register(
+    registry: &mut Handlebars<'static>,
+    template_dir: Option<&Path>,
+    name: &str,
+    default_template: &str,
+) -> crate::Result<()> {
+    let custom_template = match template_dir {
+        Some(dir) => {
+            let file = dir.join(format!("{name}.hbs", name = name));
+            if file.is_file() {
+                Some(fs::read_to_string(file)?)
+            } else {
+                None
+            }
+        }
+        None => None,
+    };
+    let template = custom_template.as_deref().unwrap_or(default_template);
+
+    let template = if name.ends_with("entry") {
+        template.trim_end_matches(['\n', '\r'])
+    } else {
+        template
+    };
+
+    registry
+        .register_template_string(name, template)
+        .with_context(|_| crate::ErrorKind::Template {
+            name: name.to_owned(),
+        })
+}
+
+/// The registered release and entry templates.
+pub struct Templates {
+    registry: Handlebars<'static>,
+}
+
+impl Templates {
+    /**
+     * 加载模板，template_dir 中存在的模板会覆盖默认模板
+     */
+    pub fn new(template_dir: Option<&Path>) -> crate::Result<Templates> {
+        let mut registry = Handlebars::new();
+        // 生成的是 markdown，不需要转义 html
+        registry.register_escape_fn(handlebars::no_escape);
+        registry.set_strict_mode(true);
+
+        for (name, default_template) in [("release", RELEASE_TEMPLATE), ("entry", ENTRY_TEMPLATE)] {
+            register(&mut registry, template_dir, name, default_template)?;
+        }
+
+        Ok(Templates { registry })
+    }
+
+    /// Render every release of a package, separated by a blank line.
+    pub fn render_package(&self, package: &PackageChangelog) -> crate::Result<String> {
+        let release_list = package
+            .releases
+            .iter()
+            .map(|release| self.render_release(&ReleaseContext::new(&package.name, release)))
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        Ok(release_list.join("\n\n"))
+    }
+
+    /// Render one release with `release.hbs`.
+    pub fn render_release(&self, release: &ReleaseContext) -> crate::Result<String> {
+        self.registry
+            .render("release", release)
+            .context(crate::ErrorKind::Template {
+                name: "release".to_owned(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The markdown written before the templates, two releases of `all`.
+    const GOLDEN: &str = "## @ant-design/pro-layout@6.5.0
+
+`2021-10-18`
+
+### Features
+
+* feat(layout): mix support headerContent render (#123). [#123](https://github.com/ant-design/pro-components/pull/123) [@chenshuai2144](https://github.com/chenshuai2144)
+
+### Bug Fixes
+
+* fix(layout): menu collapse. [1a2b3c4](https://github.com/ant-design/pro-components/commit/1a2b3c4)
+* fix(layout,form): header height (#120). [#120](https://github.com/ant-design/pro-components/pull/120) [@alice](https://github.com/alice)
+
+
+## @ant-design/pro-layout@6.4.0
+
+`2021-10-01`
+
+### Features
+
+* feat(layout): first. [9f8e7d6](https://github.com/ant-design/pro-components/commit/9f8e7d6)
+";
+
+    fn entry(message: &str, hash: &str, pr: Option<(u64, &str)>) -> Entry {
+        let repo_url = "https://github.com/ant-design/pro-components";
+        Entry {
+            commit_type: message.split('(').next().unwrap().to_owned(),
+            scopes: vec!["layout".to_owned()],
+            subject: message.split_once(": ").unwrap().1.to_owned(),
+            message: message.to_owned(),
+            breaking: false,
+            hash: hash.to_owned(),
+            commit_url: format!("{}/commit/{}", repo_url, &hash[..7]),
+            pr: pr.map(|(number, _)| number),
+            pr_reference: pr.map(|(number, _)| format!("#{}", number)),
+            pr_url: pr.map(|(number, _)| format!("{}/pull/{}", repo_url, number)),
+            authors: pr
+                .map(|(_, login)| Author {
+                    login: login.to_owned(),
+                    url: Some(format!("https://github.com/{}", login)),
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn section(title: &str, entries: Vec<Entry>) -> Section {
+        Section {
+            title: title.to_owned(),
+            entries,
+        }
+    }
+
+    #[test]
+    fn default_templates_match_the_golden_output() {
+        let package = PackageChangelog {
+            name: "@ant-design/pro-layout".to_owned(),
+            scope: "layout".to_owned(),
+            releases: vec![
+                Release {
+                    tag: "@ant-design/pro-layout@6.5.0".to_owned(),
+                    version: "6.5.0".to_owned(),
+                    date: "2021-10-18".to_owned(),
+                    sections: vec![
+                        section(
+                            "Features",
+                            vec![entry(
+                                "feat(layout): mix support headerContent render (#123)",
+                                "5d4c3b2a1f",
+                                Some((123, "chenshuai2144")),
+                            )],
+                        ),
+                        section(
+                            "Bug Fixes",
+                            vec![
+                                entry("fix(layout): menu collapse", "1a2b3c4d5e", None),
+                                entry(
+                                    "fix(layout,form): header height (#120)",
+                                    "6e5d4c3b2a",
+                                    Some((120, "alice")),
+                                ),
+                            ],
+                        ),
+                    ],
+                    unreleased: false,
+                },
+                Release {
+                    tag: "@ant-design/pro-layout@6.4.0".to_owned(),
+                    version: "6.4.0".to_owned(),
+                    date: "2021-10-01".to_owned(),
+                    sections: vec![section(
+                        "Features",
+                        vec![entry("feat(layout): first", "9f8e7d6c5b", None)],
+                    )],
+                    unreleased: false,
+                },
+            ],
+        };
+
+        let markdown = Templates::new(None)
+            .unwrap()
+            .render_package(&package)
+            .unwrap();
+        assert_eq!(markdown, GOLDEN);
+    }
+}
@@ -0,0 +1,187 @@
+//! Merging generated releases into existing changelog files.
+
+use crate::model::UNRELEASED;
+use similar::TextDiff;
+
+/// The prefix of a release heading, `## @ant-design/pro-layout@6.5.0`.
+const RELEASE_HEADING: &str = "## ";
+
+/// A `## tag` block of a changelog.
+#[derive(Clone, Debug)]
+pub struct ReleaseBlock {
+    /// The heading without the `## ` prefix, usually the tag.
+    pub heading: String,
+    /// The whole block, heading included.
+    pub content: String,
+}
+
+/// Split a changelog into the text before the first release and the release blocks.
+pub fn split_releases(content: &str) -> (String, Vec<ReleaseBlock>) {
+    let mut preamble = String::new();
+    let mut release_list: Vec<ReleaseBlock> = vec![];
+
+    for line in content.split_inclusive('\n') {
+        if let Some(heading) = line.strip_prefix(RELEASE_HEADING) {
+            release_list.push(ReleaseBlock {
+                heading: heading.trim().to_owned(),
+                content: String::new(),
+            });
+        }
+        match release_list.last_mut() {
+            Some(release) => release.content.push_str(line),
+            None => preamble.push_str(line),
+        }
+    }
+
+    (preamble, release_list)
+}
+
+/// The headings of the releases already written in a changelog.
+pub fn release_headings(content: &str) -> Vec<String> {
+    split_releases(content)
+        .1
+        .into_iter()
+        .map(|release| release.heading)
+        .collect()
+}
+
+/**
+ * 把新生成的 changelog 合并到已有的文件中
+ * 只插入文件中还没有的 release，插入在第一个 release 之前，其余的内容保持不变
+ * 所以重复执行不会修改文件，## Unreleased 每次都会替换成新生成的
+ */
+pub fn merge(existing: &str, generated: &str) -> String {
+    let existing_heading_list = release_headings(existing);
+
+    let new_release_list = split_releases(generated)
+        .1
+        .into_iter()
+        .filter(|release| {
+            release.heading == UNRELEASED || !existing_heading_list.contains(&release.heading)
+        })
+        .map(|release| release.content.trim_end().to_owned() + "\n")
+        .collect::<Vec<_>>();
+
+    if new_release_list.is_empty() {
+        return existing.to_owned();
+    }
+
+    // 之前的 Unreleased 已经过期了，被新生成的替换，或者已经发布成了新的 release
+    let (preamble, mut release_list) = split_releases(existing);
+    release_list.retain(|release| release.heading != UNRELEASED);
+
+    let mut content = preamble;
+    // 标题等内容和 release 之间空一行
+    if !content.is_empty() {
+        while !content.ends_with("\n\n") {
+            content.push('\n');
+        }
+    }
+    content.push_str(&new_release_list.join("\n\n"));
+    if !release_list.is_empty() {
+        content.push_str("\n\n");
+    }
+    for release in release_list {
+        content.push_str(&release.content);
+    }
+
+    content
+}
+
+/// A unified diff of a changelog file, empty when nothing changed.
+pub fn unified_diff(path: &str, old_content: &str, new_content: &str) -> String {
+    TextDiff::from_lines(old_content, new_content)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 和 template::Templates::render_package 一样，release 之间空两行
+    const EXISTING: &str = "# Changelog\n\
+                            \n\
+                            ## pkg@1.1.0\n\
+                            \n\
+                            * fix: edited by hand\n\
+                            \n\
+                            \n\
+                            ## pkg@1.0.0\n\
+                            \n\
+                            * feat: first\n";
+
+    #[test]
+    fn inserts_only_new_releases() {
+        let generated = "## pkg@1.2.0\n\n* feat: new\n\n\n## pkg@1.1.0\n\n* fix: generated\n";
+
+        assert_eq!(
+            merge(EXISTING, generated),
+            "# Changelog\n\
+             \n\
+             ## pkg@1.2.0\n\
+             \n\
+             * feat: new\n\
+             \n\
+             \n\
+             ## pkg@1.1.0\n\
+             \n\
+             * fix: edited by hand\n\
+             \n\
+             \n\
+             ## pkg@1.0.0\n\
+             \n\
+             * feat: first\n"
+        );
+    }
+
+    #[test]
+    fn merge_is_idempotent() {
+        for generated in [
+            "## pkg@1.2.0\n\n* feat: new\n",
+            "## Unreleased\n\n* fix: pending\n\n\n## pkg@1.2.0\n\n* feat: new\n",
+            "## pkg@1.1.0\n\n* fix: generated\n",
+        ] {
+            let once = merge(EXISTING, generated);
+            assert_eq!(merge(&once, generated), once, "{}", generated);
+        }
+    }
+
+    #[test]
+    fn nothing_new_keeps_the_file() {
+        assert_eq!(merge(EXISTING, "## pkg@1.0.0\n\n* feat: other\n"), EXISTING);
+        assert_eq!(merge(EXISTING, ""), EXISTING);
+    }
+
+    #[test]
+    fn replaces_unreleased() {
+        let existing = merge(EXISTING, "## Unreleased\n\n* fix: old\n");
+        assert!(existing.contains("* fix: old"));
+
+        let merged = merge(&existing, "## Unreleased\n\n* fix: new\n");
+        assert!(merged.contains("* fix: new"));
+        assert!(!merged.contains("* fix: old"));
+        assert_eq!(release_headings(&merged)[0], UNRELEASED);
+
+        // 发布之后 Unreleased 变成了新的 release
+        let released = merge(&existing, "## pkg@1.2.0\n\n* fix: old\n");
+        assert_eq!(
+            release_headings(&released),
+            vec!["pkg@1.2.0", "pkg@1.1.0", "pkg@1.0.0"]
+        );
+    }
+
+    #[test]
+    fn merge_into_empty_file() {
+        let generated = "## pkg@1.0.0\n\n* feat: first\n";
+        assert_eq!(merge("", generated), generated);
+    }
+
+    #[test]
+    fn diff_of_unchanged_file_is_empty() {
+        assert_eq!(unified_diff("pkg.md", EXISTING, EXISTING), "");
+        assert!(unified_diff("pkg.md", "", "a\n").starts_with("--- a/pkg.md\n+++ b/pkg.md\n"));
+    }
+}